@@ -31,9 +31,7 @@ export function activate(context: vscode.ExtensionContext) {
 
     const clientOptions: LanguageClientOptions = {
         documentSelector: [{ scheme: 'file', language: 'cooklang' }],
-        synchronize: {
-            fileEvents: vscode.workspace.createFileSystemWatcher('**/*.cook'),
-        },
+        // The server registers the files it wants watched
     };
 
     client = new LanguageClient(
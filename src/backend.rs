@@ -1,54 +1,382 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
-use tower_lsp::jsonrpc::Result;
+use tower_lsp::jsonrpc::{Error, Result};
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer};
 
 use crate::completion;
+use crate::data;
 use crate::diagnostics;
+use crate::format;
 use crate::hover;
+use crate::i18n::Language;
+use crate::index;
+use crate::project_config;
+use crate::quick_fixes;
 use crate::semantic_tokens;
+use crate::settings::{self, Settings};
+use crate::shopping_list;
 use crate::state::ServerState;
 use crate::symbols;
+use crate::utils::position::position_to_offset;
+
+/// How long to wait after an edit before checking the document; another
+/// edit in the meantime starts the wait again
+const DIAGNOSTICS_DELAY: Duration = Duration::from_millis(200);
+
+/// Id of the `workspace/didChangeWatchedFiles` registration
+const WATCHED_FILES_REGISTRATION: &str = "cooklang-watched-files";
 
 pub struct Backend {
     client: Client,
-    state: ServerState,
+    state: Arc<ServerState>,
     /// Workspace root path for loading configuration files
     workspace_root: std::sync::RwLock<Option<PathBuf>>,
+    /// Settings from the client, before `.cooklang.toml` is applied
+    client_settings: std::sync::RwLock<Settings>,
+    /// Locale of the client UI, from `initialize`
+    client_locale: std::sync::RwLock<Option<String>>,
+    /// Whether the client answers `workspace/configuration` requests
+    supports_configuration: std::sync::atomic::AtomicBool,
+    /// Whether the client pulls diagnostics, so they aren't pushed
+    pulls_diagnostics: std::sync::atomic::AtomicBool,
+    /// Whether the client handles `workspace/diagnostic/refresh` requests
+    supports_diagnostic_refresh: std::sync::atomic::AtomicBool,
+    /// Whether the client lets us register the files it watches for us
+    supports_file_watching: std::sync::atomic::AtomicBool,
+    /// Globs of the files the client watches for us, empty when unregistered
+    watched_files: std::sync::Mutex<Vec<String>>,
 }
 
 impl Backend {
     pub fn new(client: Client) -> Self {
         Self {
             client,
-            state: ServerState::new(),
+            state: Arc::new(ServerState::new()),
             workspace_root: std::sync::RwLock::new(None),
+            client_settings: std::sync::RwLock::new(Settings::default()),
+            client_locale: std::sync::RwLock::new(None),
+            supports_configuration: std::sync::atomic::AtomicBool::new(false),
+            pulls_diagnostics: std::sync::atomic::AtomicBool::new(false),
+            supports_diagnostic_refresh: std::sync::atomic::AtomicBool::new(false),
+            supports_file_watching: std::sync::atomic::AtomicBool::new(false),
+            watched_files: std::sync::Mutex::new(Vec::new()),
         }
     }
 
-    /// Try to load aisle.conf from the workspace
-    fn load_aisle_config(&self) {
+    /// Try to load aisle.conf, pantry.conf, completion data and recipe
+    /// templates from the workspace
+    fn load_workspace_config(&self) {
         if let Ok(guard) = self.workspace_root.read() {
             if let Some(ref path) = *guard {
                 self.state.load_aisle_config(path);
+                self.state.load_pantry_config(path);
             }
         }
+        self.state.load_completion_data(self.data_dir().as_deref());
+        self.state.load_templates(self.template_dir().as_deref());
     }
 
-    async fn publish_diagnostics(&self, uri: &Url) {
-        let diagnostics = if let Some(doc) = self.state.get_document(uri) {
-            diagnostics::get_diagnostics(&doc)
-        } else {
-            vec![]
+    /// The completion data directory of the workspace, if there is a workspace
+    fn data_dir(&self) -> Option<PathBuf> {
+        let root = self.workspace_root.read().ok()?.clone()?;
+        Some(root.join(self.state.settings().data_directory()))
+    }
+
+    /// The recipe template directory of the workspace, if there is a workspace
+    fn template_dir(&self) -> Option<PathBuf> {
+        let root = self.workspace_root.read().ok()?.clone()?;
+        Some(root.join(self.state.settings().template_directory()))
+    }
+
+    /// Globs of the files the server reads from the workspace: recipes,
+    /// `.cooklang.toml`, aisle and pantry configuration, completion data and
+    /// templates
+    fn watched_globs(&self) -> Vec<String> {
+        let Some(root) = self.workspace_root.read().ok().and_then(|r| r.clone()) else {
+            return Vec::new();
+        };
+        let glob = |path: &Path| path.to_string_lossy().replace('\\', "/");
+        let mut globs = vec![
+            glob(&root.join("**/*.{cook,menu}")),
+            glob(&root.join(project_config::FILE_NAME)),
+        ];
+        globs.extend(self.state.aisle_config_paths(&root).iter().map(|p| glob(p)));
+        globs.extend(
+            self.state
+                .pantry_config_paths(&root)
+                .iter()
+                .map(|p| glob(p)),
+        );
+        if let Some(dir) = self.data_dir() {
+            globs.push(glob(&dir.join("*")));
+        }
+        if let Some(dir) = self.template_dir() {
+            globs.push(glob(&dir.join("**")));
+        }
+        globs
+    }
+
+    /// Ask the client to watch the files the server reads, again when the
+    /// settings or the workspace moved them
+    async fn watch_files(&self) {
+        if !self
+            .supports_file_watching
+            .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            return;
+        }
+        let globs = self.watched_globs();
+        let previous = match self.watched_files.lock() {
+            Ok(mut guard) if *guard != globs => std::mem::replace(&mut *guard, globs.clone()),
+            _ => return,
         };
 
-        self.client
-            .publish_diagnostics(uri.clone(), diagnostics, None)
-            .await;
+        if !previous.is_empty() {
+            let unregistration = Unregistration {
+                id: WATCHED_FILES_REGISTRATION.into(),
+                method: "workspace/didChangeWatchedFiles".into(),
+            };
+            if let Err(err) = self
+                .client
+                .unregister_capability(vec![unregistration])
+                .await
+            {
+                tracing::warn!("Could not stop watching files: {}", err);
+            }
+        }
+        if globs.is_empty() {
+            return;
+        }
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: globs
+                .into_iter()
+                .map(|glob| FileSystemWatcher {
+                    glob_pattern: GlobPattern::String(glob),
+                    kind: None,
+                })
+                .collect(),
+        };
+        let registration = Registration {
+            id: WATCHED_FILES_REGISTRATION.into(),
+            method: "workspace/didChangeWatchedFiles".into(),
+            register_options: serde_json::to_value(options).ok(),
+        };
+        if let Err(err) = self.client.register_capability(vec![registration]).await {
+            tracing::warn!("Could not watch files: {}", err);
+        }
+    }
+
+    /// Default language for recipes without `locale` metadata: the `locale`
+    /// setting, or else the client's locale
+    fn update_language(&self) {
+        let client_locale = self.client_locale.read().ok().and_then(|l| l.clone());
+        let language = self
+            .state
+            .settings()
+            .locale
+            .or(client_locale)
+            .and_then(|locale| Language::from_locale(&locale))
+            .unwrap_or_default();
+        if let Ok(mut guard) = self.state.language.write() {
+            *guard = language;
+        }
+    }
+
+    /// The `cooklang` section of the client configuration, if the client
+    /// supports `workspace/configuration` and has one
+    async fn pull_settings(&self) -> Option<Settings> {
+        if !self
+            .supports_configuration
+            .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            return None;
+        }
+        let items = vec![ConfigurationItem {
+            scope_uri: None,
+            section: Some(settings::SECTION.into()),
+        }];
+        match self.client.configuration(items).await {
+            Ok(values) => {
+                let value = values.into_iter().next().filter(|value| !value.is_null())?;
+                Some(self.read_settings(value).await)
+            }
+            Err(err) => {
+                tracing::warn!("Could not read client configuration: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Settings from the client; the user is told about invalid ones
+    async fn read_settings(&self, value: serde_json::Value) -> Settings {
+        let (settings, problems) = Settings::parse(value);
+        for problem in problems {
+            tracing::warn!("{}", problem);
+            self.client
+                .show_message(MessageType::WARNING, problem)
+                .await;
+        }
+        settings
+    }
+
+    fn set_client_settings(&self, settings: Settings) {
+        if let Ok(mut guard) = self.client_settings.write() {
+            *guard = settings;
+        }
+    }
+
+    /// The client settings overridden by the workspace's `.cooklang.toml`.
+    /// Problems in that file are published as its diagnostics.
+    async fn resolve_settings(&self) -> Settings {
+        let mut settings = self
+            .client_settings
+            .read()
+            .map(|settings| settings.clone())
+            .unwrap_or_default();
+        let root = self
+            .workspace_root
+            .read()
+            .ok()
+            .and_then(|root| root.clone());
+        let Some(project) = root.and_then(|root| project_config::load(&root)) else {
+            return settings;
+        };
+
+        project.config.apply(&mut settings);
+        if let Ok(uri) = Url::from_file_path(&project.path) {
+            self.client
+                .publish_diagnostics(uri, project.diagnostics, None)
+                .await;
+        }
+        settings
+    }
+
+    /// Resolve the settings again and apply them if they changed: reload
+    /// what depends on them and re-check every open document
+    async fn change_settings(&self) {
+        let settings = self.resolve_settings().await;
+        if settings == self.state.settings() {
+            return;
+        }
+        tracing::info!("Settings changed: {:?}", settings);
+        self.state.apply_settings(settings);
+        self.update_language();
+        self.load_workspace_config();
+        self.watch_files().await;
+        self.refresh_diagnostics().await;
+    }
+
+    /// Diagnostics may have changed in any recipe (settings, or recipes
+    /// referenced from others): ask a client that pulls them to pull again,
+    /// or push them again for every open document
+    async fn refresh_diagnostics(&self) {
+        if self.pulls_diagnostics() {
+            if self
+                .supports_diagnostic_refresh
+                .load(std::sync::atomic::Ordering::Relaxed)
+            {
+                if let Err(err) = self.client.workspace_diagnostic_refresh().await {
+                    tracing::warn!("Could not refresh diagnostics: {}", err);
+                }
+            }
+            return;
+        }
+
+        let open: Vec<Url> = self
+            .state
+            .documents
+            .iter()
+            .map(|e| e.key().clone())
+            .collect();
+        for uri in open {
+            self.publish_diagnostics(&uri).await;
+        }
+    }
+
+    fn pulls_diagnostics(&self) -> bool {
+        self.pulls_diagnostics
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Index the recipes in the workspace, on a blocking thread as reading
+    /// a large workspace takes a while, then check the recipes again:
+    /// references to other recipes may resolve now
+    async fn index_workspace(&self) {
+        let root = self
+            .workspace_root
+            .read()
+            .ok()
+            .and_then(|root| root.clone());
+        match root {
+            Some(root) => {
+                let state = Arc::clone(&self.state);
+                let indexing = tokio::task::spawn_blocking(move || state.index_workspace(&root));
+                if let Err(err) = indexing.await {
+                    tracing::warn!("Could not index the workspace: {}", err);
+                }
+            }
+            None => self.state.index.clear(),
+        }
+        self.refresh_diagnostics().await;
+    }
+
+    /// `cooklang/tags`: every tag used in the workspace and the recipes using it
+    pub async fn tags(&self) -> Result<BTreeMap<String, Vec<Url>>> {
+        Ok(self.state.tag_index())
+    }
+
+    /// The workspace root, falling back to the document's parent directory
+    /// when no workspace root is provided (e.g. when launched via cookcli web)
+    fn workspace_root_for(&self, uri: &Url) -> Option<PathBuf> {
+        self.workspace_root
+            .read()
+            .ok()
+            .and_then(|guard| guard.clone())
+            .or_else(|| {
+                uri.to_file_path()
+                    .ok()
+                    .and_then(|p| p.parent().map(|p| p.to_path_buf()))
+            })
+    }
+
+    /// Push the diagnostics of `uri`, unless the client pulls them
+    async fn publish_diagnostics(&self, uri: &Url) {
+        if self.pulls_diagnostics() {
+            return;
+        }
+        let workspace_root = self.workspace_root_for(uri);
+        publish_diagnostics(&self.client, &self.state, uri, workspace_root.as_deref()).await;
     }
 }
 
+/// Check `uri` and push its diagnostics, unless the document changed in the
+/// meantime: the diagnostics of the newer version are pushed instead
+async fn publish_diagnostics(
+    client: &Client,
+    state: &ServerState,
+    uri: &Url,
+    workspace_root: Option<&Path>,
+) {
+    let (diagnostics, version) = match state.get_document(uri) {
+        Some(doc) => (
+            diagnostics::get_diagnostics(&doc, workspace_root, state),
+            Some(doc.version),
+        ),
+        None => (vec![], None),
+    };
+    if version.is_some() && state.latest_version(uri) != version {
+        tracing::debug!("Dropping diagnostics of stale version of {}", uri);
+        return;
+    }
+    client
+        .publish_diagnostics(uri.clone(), diagnostics, version)
+        .await;
+}
+
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
     async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
@@ -70,6 +398,53 @@ impl LanguageServer for Backend {
                 params.root_path.as_ref().map(PathBuf::from)
             });
 
+        let settings = self
+            .read_settings(params.initialization_options.unwrap_or_default())
+            .await;
+        self.set_client_settings(settings.clone());
+        self.state.apply_settings(settings);
+        if let Ok(mut guard) = self.client_locale.write() {
+            *guard = params.locale;
+        }
+        self.update_language();
+
+        let supports_configuration = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.configuration)
+            .unwrap_or(false);
+        self.supports_configuration
+            .store(supports_configuration, std::sync::atomic::Ordering::Relaxed);
+
+        let pulls_diagnostics = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .is_some_and(|text_document| text_document.diagnostic.is_some());
+        let supports_diagnostic_refresh = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.diagnostic.as_ref())
+            .and_then(|diagnostic| diagnostic.refresh_support)
+            .unwrap_or(false);
+        let supports_file_watching = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.did_change_watched_files)
+            .and_then(|watched_files| watched_files.dynamic_registration)
+            .unwrap_or(false);
+        self.supports_file_watching
+            .store(supports_file_watching, std::sync::atomic::Ordering::Relaxed);
+        self.pulls_diagnostics
+            .store(pulls_diagnostics, std::sync::atomic::Ordering::Relaxed);
+        self.supports_diagnostic_refresh.store(
+            supports_diagnostic_refresh,
+            std::sync::atomic::Ordering::Relaxed,
+        );
+
         if let Some(path) = workspace_path {
             tracing::info!("Workspace root: {:?}", path);
             if let Ok(mut guard) = self.workspace_root.write() {
@@ -98,13 +473,37 @@ impl LanguageServer for Backend {
                         "{".into(),
                         ".".into(),
                         "/".into(),
+                        ">".into(),
+                        ":".into(),
+                        "&".into(),
+                        "|".into(),
                     ]),
-                    resolve_provider: Some(false),
+                    resolve_provider: Some(true),
                     ..Default::default()
                 }),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Options(
+                    CodeActionOptions {
+                        code_action_kinds: Some(vec![CodeActionKind::QUICKFIX]),
+                        ..Default::default()
+                    },
+                )),
+                diagnostic_provider: Some(DiagnosticServerCapabilities::Options(
+                    DiagnosticOptions {
+                        identifier: Some("cooklang".into()),
+                        // Recipes reference other recipes
+                        inter_file_dependencies: true,
+                        workspace_diagnostics: true,
+                        ..Default::default()
+                    },
+                )),
                 document_symbol_provider: Some(OneOf::Left(true)),
+                document_formatting_provider: Some(OneOf::Left(true)),
                 semantic_tokens_provider: Some(semantic_tokens::capabilities()),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![shopping_list::COMMAND.into()],
+                    ..Default::default()
+                }),
                 workspace: Some(WorkspaceServerCapabilities {
                     workspace_folders: Some(WorkspaceFoldersServerCapabilities {
                         supported: Some(true),
@@ -124,12 +523,23 @@ impl LanguageServer for Backend {
     async fn initialized(&self, _: InitializedParams) {
         tracing::info!("Cooklang LSP initialized");
 
-        // Load aisle.conf if available in workspace
-        self.load_aisle_config();
+        // Client configuration takes precedence over initializationOptions,
+        // and .cooklang.toml over both
+        if let Some(settings) = self.pull_settings().await {
+            self.set_client_settings(settings);
+        }
+        let settings = self.resolve_settings().await;
+        self.state.apply_settings(settings);
+        self.update_language();
+
+        // Load aisle.conf and pantry.conf if available in workspace
+        self.load_workspace_config();
+        self.watch_files().await;
 
         self.client
             .log_message(MessageType::INFO, "Cooklang Language Server initialized")
             .await;
+        self.index_workspace().await;
     }
 
     async fn shutdown(&self) -> Result<()> {
@@ -156,8 +566,86 @@ impl LanguageServer for Backend {
             *guard = new_root;
         }
 
-        // Reload aisle.conf for the new workspace (or clear it if the root is gone).
-        self.load_aisle_config();
+        // Apply the new workspace's .cooklang.toml and reload aisle.conf (or
+        // clear it if the root is gone).
+        let settings = self.resolve_settings().await;
+        self.state.apply_settings(settings);
+        self.update_language();
+        self.load_workspace_config();
+        self.watch_files().await;
+        self.index_workspace().await;
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        // Clients either push the new settings or expect them to be pulled
+        let settings = match params.settings.get(settings::SECTION) {
+            Some(value) => Some(self.read_settings(value.clone()).await),
+            None => self.pull_settings().await,
+        };
+        if let Some(settings) = settings {
+            self.set_client_settings(settings);
+            self.change_settings().await;
+        }
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        let root = self
+            .workspace_root
+            .read()
+            .ok()
+            .and_then(|root| root.clone());
+        let template_dir = self.template_dir();
+        let data_dir = self.data_dir();
+        let mut recipes_changed = false;
+        for change in params.changes {
+            let Ok(path) = change.uri.to_file_path() else {
+                continue;
+            };
+            if root
+                .as_ref()
+                .is_some_and(|root| project_config::is_project_file(&path, root))
+            {
+                tracing::debug!("Project configuration changed: {}", change.uri);
+                if change.typ == FileChangeType::DELETED {
+                    self.client
+                        .publish_diagnostics(change.uri, vec![], None)
+                        .await;
+                }
+                self.change_settings().await;
+            } else if let Some(root) = root
+                .as_ref()
+                .filter(|root| self.state.aisle_config_paths(root).contains(&path))
+            {
+                tracing::debug!("Aisle configuration changed: {}", change.uri);
+                self.state.load_aisle_config(root);
+            } else if let Some(root) = root
+                .as_ref()
+                .filter(|root| self.state.pantry_config_paths(root).contains(&path))
+            {
+                tracing::debug!("Pantry configuration changed: {}", change.uri);
+                self.state.load_pantry_config(root);
+            } else if data_dir
+                .as_ref()
+                .is_some_and(|dir| data::is_data_file(&path, dir))
+            {
+                tracing::debug!("Completion data changed: {}", change.uri);
+                self.state.load_completion_data(data_dir.as_deref());
+            } else if template_dir
+                .as_ref()
+                .is_some_and(|dir| path.starts_with(dir))
+            {
+                tracing::debug!("Recipe template changed: {}", change.uri);
+                self.state.load_templates(template_dir.as_deref());
+            } else if index::is_recipe_file(&path) {
+                tracing::debug!("Recipe changed on disk: {}", change.uri);
+                self.state.reindex(&change.uri);
+                recipes_changed = true;
+            }
+        }
+        // References to the recipe may now resolve, or not
+        if recipes_changed {
+            self.refresh_diagnostics().await;
+        }
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
@@ -174,11 +662,27 @@ impl LanguageServer for Backend {
         let uri = params.text_document.uri;
         let version = params.text_document.version;
 
-        if let Some(change) = params.content_changes.into_iter().last() {
-            tracing::debug!("Document changed: {}", uri);
-            self.state.update_document(&uri, version, change.text);
-            self.publish_diagnostics(&uri).await;
+        let Some(change) = params.content_changes.into_iter().last() else {
+            return;
+        };
+        tracing::debug!("Document changed: {}", uri);
+        // Parsed when next needed: by a request, or to push diagnostics once
+        // the edits stop
+        self.state.queue_change(&uri, version, change.text);
+        if self.pulls_diagnostics() {
+            return;
         }
+
+        let client = self.client.clone();
+        let state = Arc::clone(&self.state);
+        let workspace_root = self.workspace_root_for(&uri);
+        tokio::spawn(async move {
+            tokio::time::sleep(DIAGNOSTICS_DELAY).await;
+            // A later edit has its own wait
+            if state.latest_version(&uri) == Some(version) {
+                publish_diagnostics(&client, &state, &uri, workspace_root.as_deref()).await;
+            }
+        });
     }
 
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
@@ -190,23 +694,14 @@ impl LanguageServer for Backend {
         let uri = params.text_document.uri;
         tracing::debug!("Document closed: {}", uri);
         self.state.close_document(&uri);
-        self.client.publish_diagnostics(uri, vec![], None).await;
+        if !self.pulls_diagnostics() {
+            self.client.publish_diagnostics(uri, vec![], None).await;
+        }
     }
 
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
         let uri = &params.text_document_position.text_document.uri;
-        let workspace_root = self
-            .workspace_root
-            .read()
-            .ok()
-            .and_then(|guard| guard.clone())
-            .or_else(|| {
-                // Fall back to the document's parent directory when no workspace root
-                // is provided (e.g. when launched via cookcli web)
-                uri.to_file_path()
-                    .ok()
-                    .and_then(|p| p.parent().map(|p| p.to_path_buf()))
-            });
+        let workspace_root = self.workspace_root_for(uri);
 
         let response = if let Some(doc) = self.state.get_document(uri) {
             completion::get_completions(&doc, &params, &self.state, workspace_root.as_deref())
@@ -217,11 +712,21 @@ impl LanguageServer for Backend {
         Ok(response)
     }
 
+    async fn completion_resolve(&self, item: CompletionItem) -> Result<CompletionItem> {
+        let workspace_root = self.workspace_root.read().ok().and_then(|r| r.clone());
+        Ok(completion::resolve_completion(
+            item,
+            &self.state,
+            workspace_root.as_deref(),
+        ))
+    }
+
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
         let uri = &params.text_document_position_params.text_document.uri;
+        let workspace_root = self.workspace_root_for(uri);
 
         let response = if let Some(doc) = self.state.get_document(uri) {
-            hover::get_hover(&doc, &params)
+            hover::get_hover(&doc, &params, &self.state, workspace_root.as_deref())
         } else {
             None
         };
@@ -229,6 +734,64 @@ impl LanguageServer for Backend {
         Ok(response)
     }
 
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = &params.text_document.uri;
+
+        let response = self.state.get_document(uri).map(|doc| {
+            let start = position_to_offset(params.range.start, &doc.line_index);
+            let end = position_to_offset(params.range.end, &doc.line_index);
+            quick_fixes::code_actions(&doc, start..end, &self.state)
+        });
+
+        Ok(response.filter(|actions| !actions.is_empty()))
+    }
+
+    async fn diagnostic(
+        &self,
+        params: DocumentDiagnosticParams,
+    ) -> Result<DocumentDiagnosticReportResult> {
+        let uri = &params.text_document.uri;
+        let workspace_root = self.workspace_root_for(uri);
+
+        let (diagnostics, _) =
+            diagnostics::recipe_diagnostics(uri, workspace_root.as_deref(), &self.state)
+                .unwrap_or_default();
+
+        let report = match diagnostics::report(diagnostics, params.previous_result_id.as_deref()) {
+            DocumentDiagnosticReportKind::Full(full_document_diagnostic_report) => {
+                DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+                    related_documents: None,
+                    full_document_diagnostic_report,
+                })
+            }
+            DocumentDiagnosticReportKind::Unchanged(unchanged_document_diagnostic_report) => {
+                DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+                    related_documents: None,
+                    unchanged_document_diagnostic_report,
+                })
+            }
+        };
+        Ok(DocumentDiagnosticReportResult::Report(report))
+    }
+
+    async fn workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> Result<WorkspaceDiagnosticReportResult> {
+        let workspace_root = self
+            .workspace_root
+            .read()
+            .ok()
+            .and_then(|root| root.clone());
+        let report = diagnostics::workspace_report(
+            &self.state,
+            workspace_root.as_deref(),
+            &params.previous_result_ids,
+        )
+        .await;
+        Ok(WorkspaceDiagnosticReportResult::Report(report))
+    }
+
     async fn document_symbol(
         &self,
         params: DocumentSymbolParams,
@@ -244,6 +807,18 @@ impl LanguageServer for Backend {
         Ok(response)
     }
 
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let uri = &params.text_document.uri;
+        let settings = self.state.settings().format;
+
+        let response = self
+            .state
+            .get_document(uri)
+            .map(|doc| format::edits(&doc, &settings));
+
+        Ok(response)
+    }
+
     async fn semantic_tokens_full(
         &self,
         params: SemanticTokensParams,
@@ -251,14 +826,72 @@ impl LanguageServer for Backend {
         let uri = &params.text_document.uri;
 
         let tokens = if let Some(doc) = self.state.get_document(uri) {
-            semantic_tokens::get_semantic_tokens(&doc)
+            self.state.semantic_tokens.full(&doc)
         } else {
-            vec![]
+            SemanticTokens::default()
         };
 
-        Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
-            result_id: None,
-            data: tokens,
-        })))
+        Ok(Some(SemanticTokensResult::Tokens(tokens)))
+    }
+
+    async fn semantic_tokens_full_delta(
+        &self,
+        params: SemanticTokensDeltaParams,
+    ) -> Result<Option<SemanticTokensFullDeltaResult>> {
+        let uri = &params.text_document.uri;
+
+        let response = self.state.get_document(uri).map(|doc| {
+            self.state
+                .semantic_tokens
+                .full_delta(&doc, &params.previous_result_id)
+        });
+
+        Ok(response)
+    }
+
+    async fn semantic_tokens_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> Result<Option<SemanticTokensRangeResult>> {
+        let uri = &params.text_document.uri;
+
+        let response = self.state.get_document(uri).map(|doc| {
+            SemanticTokensRangeResult::Tokens(self.state.semantic_tokens.range(&doc, params.range))
+        });
+
+        Ok(response)
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        match params.command.as_str() {
+            shopping_list::COMMAND => {
+                let args: shopping_list::ShoppingListParams = params
+                    .arguments
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| Error::invalid_params("Missing shopping list arguments"))
+                    .and_then(|arg| {
+                        serde_json::from_value(arg)
+                            .map_err(|err| Error::invalid_params(err.to_string()))
+                    })?;
+                let workspace_root = args
+                    .recipes
+                    .first()
+                    .and_then(|entry| self.workspace_root_for(entry.uri()));
+
+                Ok(Some(shopping_list::execute(
+                    args,
+                    &self.state,
+                    workspace_root.as_deref(),
+                )))
+            }
+            _ => Err(Error::invalid_params(format!(
+                "Unknown command: {}",
+                params.command
+            ))),
+        }
     }
 }
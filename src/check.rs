@@ -0,0 +1,363 @@
+//! `cooklang-lsp check`: the editor's diagnostics, without an editor.
+//!
+//! Recipes are checked with the same settings an editor opening the
+//! workspace would use (the defaults, overridden by `.cooklang.toml`), so a
+//! CI job rejects exactly what the editor flags. Problems in `.cooklang.toml`
+//! itself are reported too.
+
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde_json::json;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Url};
+
+use crate::diagnostics;
+use crate::document::Document;
+use crate::i18n::Language;
+use crate::index;
+use crate::lints;
+use crate::project_config;
+use crate::state::ServerState;
+
+/// How the results are printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// `path:line:column: severity[code]: message`, one per line
+    #[default]
+    Human,
+    /// The LSP diagnostics of each file
+    Json,
+    /// SARIF 2.1.0, for code scanning tools
+    Sarif,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            "sarif" => Ok(Self::Sarif),
+            _ => Err(format!(
+                "unknown format `{s}`, expected `human`, `json` or `sarif`"
+            )),
+        }
+    }
+}
+
+/// The diagnostics of a checked file
+#[derive(Debug)]
+pub struct FileReport {
+    /// As given on the command line (or found under a directory given)
+    pub path: PathBuf,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Check the recipes at `paths` (files, or directories to search for
+/// `.cook` and `.menu` files) in the workspace `root`
+pub fn check(root: &Path, paths: &[PathBuf]) -> io::Result<Vec<FileReport>> {
+    // The index only takes files it can make URIs of
+    let root = &std::path::absolute(root)?;
+    let (settings, project) = project_config::workspace_settings(root);
+    let mut reports = Vec::new();
+    if let Some(project) = project.filter(|project| !project.diagnostics.is_empty()) {
+        reports.push(FileReport {
+            path: project.path,
+            diagnostics: project.diagnostics,
+        });
+    }
+
+    let state = ServerState::new();
+    state.apply_settings(settings.clone());
+    state.load_aisle_config(root);
+    state.load_pantry_config(root);
+    state.load_completion_data(Some(&root.join(settings.data_directory())));
+    if let Some(language) = settings.locale.as_deref().and_then(Language::from_locale) {
+        if let Ok(mut guard) = state.language.write() {
+            *guard = language;
+        }
+    }
+    state.index_workspace(root);
+
+    let extensions = settings.extensions();
+    for path in index::recipe_paths(paths)? {
+        let content = std::fs::read_to_string(&path)?;
+        let absolute = std::path::absolute(&path)?;
+        let uri = Url::from_file_path(&absolute)
+            .map_err(|()| io::Error::new(io::ErrorKind::InvalidInput, "not a valid file path"))?;
+        let doc = Document::with_extensions(uri, 0, content, extensions);
+        let diagnostics = diagnostics::get_diagnostics(&doc, Some(root), &state);
+        reports.push(FileReport { path, diagnostics });
+    }
+    Ok(reports)
+}
+
+/// Whether any of the diagnostics is an error
+pub fn has_errors(reports: &[FileReport]) -> bool {
+    reports
+        .iter()
+        .flat_map(|report| &report.diagnostics)
+        .any(|diag| diag.severity == Some(DiagnosticSeverity::ERROR))
+}
+
+/// The reports printed in `format`
+pub fn render(reports: &[FileReport], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Human => human(reports),
+        OutputFormat::Json => {
+            let files: Vec<_> = reports
+                .iter()
+                .map(|report| json!({ "path": report.path, "diagnostics": report.diagnostics }))
+                .collect();
+            serde_json::to_string_pretty(&files).unwrap_or_default() + "\n"
+        }
+        OutputFormat::Sarif => {
+            serde_json::to_string_pretty(&sarif(reports)).unwrap_or_default() + "\n"
+        }
+    }
+}
+
+fn severity_name(severity: Option<DiagnosticSeverity>) -> &'static str {
+    match severity {
+        Some(DiagnosticSeverity::ERROR) => "error",
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        Some(DiagnosticSeverity::HINT) => "hint",
+        _ => "info",
+    }
+}
+
+fn code(diag: &Diagnostic) -> Option<String> {
+    match diag.code {
+        Some(NumberOrString::String(ref code)) => Some(code.clone()),
+        Some(NumberOrString::Number(code)) => Some(code.to_string()),
+        None => None,
+    }
+}
+
+fn human(reports: &[FileReport]) -> String {
+    let mut out = String::new();
+    let mut counts = [0; 2];
+    for report in reports {
+        for diag in &report.diagnostics {
+            let start = diag.range.start;
+            let severity = severity_name(diag.severity);
+            let code = code(diag)
+                .map(|code| format!("[{code}]"))
+                .unwrap_or_default();
+            // Hints of parser diagnostics go on their own lines
+            let message = diag.message.replace('\n', "\n    ");
+            let _ = writeln!(
+                out,
+                "{}:{}:{}: {severity}{code}: {message}",
+                report.path.display(),
+                start.line + 1,
+                start.character + 1,
+            );
+            match diag.severity {
+                Some(DiagnosticSeverity::ERROR) => counts[0] += 1,
+                Some(DiagnosticSeverity::WARNING) => counts[1] += 1,
+                _ => {}
+            }
+        }
+    }
+    let files = reports
+        .iter()
+        .filter(|report| index::is_recipe_file(&report.path))
+        .count();
+    let _ = writeln!(
+        out,
+        "{}, {} in {}",
+        plural(counts[0], "error"),
+        plural(counts[1], "warning"),
+        plural(files, "file")
+    );
+    out
+}
+
+fn plural(n: usize, word: &str) -> String {
+    match n {
+        1 => format!("1 {word}"),
+        n => format!("{n} {word}s"),
+    }
+}
+
+/// A SARIF 2.1.0 log with one run
+fn sarif(reports: &[FileReport]) -> serde_json::Value {
+    let rules: Vec<_> = lints::RULES
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.name,
+                "helpUri": lints::docs_url(rule),
+                "defaultConfiguration": { "level": sarif_level(Some(rule.severity)) },
+            })
+        })
+        .collect();
+
+    let results: Vec<_> = reports
+        .iter()
+        .flat_map(|report| {
+            // Relative to where the check ran, or a `file:` URI
+            let uri = match Url::from_file_path(&report.path) {
+                Ok(uri) => uri.to_string(),
+                Err(()) => report.path.to_string_lossy().replace('\\', "/"),
+            };
+            report.diagnostics.iter().map(move |diag| {
+                let range = diag.range;
+                let mut result = json!({
+                    "level": sarif_level(diag.severity),
+                    "message": { "text": diag.message },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": uri },
+                            // 1-based; columns count UTF-16 code units, like LSP
+                            "region": {
+                                "startLine": range.start.line + 1,
+                                "startColumn": range.start.character + 1,
+                                "endLine": range.end.line + 1,
+                                "endColumn": range.end.character + 1,
+                            },
+                        },
+                    }],
+                });
+                if let Some(code) = code(diag) {
+                    result["ruleId"] = code.into();
+                }
+                result
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "cooklang-lsp",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+fn sarif_level(severity: Option<DiagnosticSeverity>) -> &'static str {
+    match severity {
+        Some(DiagnosticSeverity::ERROR) => "error",
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        _ => "note",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn workspace() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let metadata = "---\ntitle: Test\nservings: 2\n---\n";
+        fs::create_dir(dir.path().join("mains")).unwrap();
+        fs::write(
+            dir.path().join("mains/pasta.cook"),
+            format!("{metadata}Serve with @./sauces/Pesto{{}}."),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("soup.cook"),
+            format!("{metadata}Boil @water{{1%l}} with @salt."),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join(project_config::FILE_NAME),
+            "[lints]\nmissing-quantity = \"warning\"\n",
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn checks_with_the_project_settings() {
+        let dir = workspace();
+        let reports = check(dir.path(), &[dir.path().to_path_buf()]).unwrap();
+        assert!(has_errors(&reports));
+
+        let output = render(&reports, OutputFormat::Human);
+        let root = dir.path().display();
+        assert_eq!(
+            output,
+            format!(
+                "{root}/mains/pasta.cook:5:12: error: Referenced recipe not found: ./sauces/Pesto\n\
+                 {root}/soup.cook:5:23: warning[missing-quantity]: No quantity for `salt`\n\
+                 1 error, 1 warning in 2 files\n"
+            )
+        );
+
+        let reports = check(dir.path(), &[dir.path().join("soup.cook")]).unwrap();
+        assert!(!has_errors(&reports));
+        assert!(check(dir.path(), &[dir.path().join("bread.cook")]).is_err());
+    }
+
+    #[test]
+    fn relative_root_indexes_the_workspace() {
+        let dir = TempDir::new().unwrap();
+        let metadata = "---\ntitle: Test\nservings: 2\n---\n";
+        fs::write(
+            dir.path().join("a.cook"),
+            format!("{metadata}Season with @yuzu kosho{{1%tsp}}."),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("b.cook"),
+            format!("{metadata}Season with @yuzu kosho."),
+        )
+        .unwrap();
+
+        // The temporary directory, relative to the current one
+        let cwd = std::env::current_dir().unwrap();
+        let up: PathBuf = cwd.components().skip(1).map(|_| "..").collect();
+        let root = up.join(dir.path().strip_prefix("/").unwrap());
+        assert!(root.is_relative());
+
+        let reports = check(&root, &[root.join("b.cook")]).unwrap();
+        // `@yuzu kosho` reads as `yuzu`, but the workspace knows `yuzu kosho`
+        let codes: Vec<_> = reports[0].diagnostics.iter().filter_map(code).collect();
+        assert!(
+            codes.iter().any(|code| code == "unbraced-name"),
+            "{codes:?}"
+        );
+    }
+
+    #[test]
+    fn json_and_sarif() {
+        let dir = workspace();
+        let reports = check(dir.path(), &[dir.path().join("soup.cook")]).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&reports, OutputFormat::Json)).unwrap();
+        assert_eq!(json[0]["diagnostics"][0]["code"], "missing-quantity");
+
+        let sarif: serde_json::Value =
+            serde_json::from_str(&render(&reports, OutputFormat::Sarif)).unwrap();
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], "missing-quantity");
+        assert_eq!(result["level"], "warning");
+        let region = &result["locations"][0]["physicalLocation"]["region"];
+        assert_eq!(
+            (region["startLine"].as_u64(), region["startColumn"].as_u64()),
+            (Some(5), Some(23))
+        );
+        assert_eq!(
+            sarif["runs"][0]["tool"]["driver"]["rules"][0]["helpUri"],
+            "https://github.com/cooklang/cooklang-language-server/blob/main/docs/lints.md#missing-quantity"
+        );
+    }
+}
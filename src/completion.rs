@@ -1,54 +1,35 @@
 use std::path::Path;
+use std::str::FromStr;
 use std::sync::LazyLock;
+
+use cooklang::metadata::StdKey;
+use serde::{Deserialize, Serialize};
 use tower_lsp::lsp_types::{
     CompletionItem, CompletionItemKind, CompletionList, CompletionParams, CompletionResponse,
-    CompletionTextEdit, Documentation, InsertTextFormat, Position, Range, TextEdit,
+    CompletionTextEdit, Documentation, InsertTextFormat, MarkupContent, MarkupKind, Position,
+    Range, TextEdit, Url,
 };
 
+use crate::data::{parse_simple_list, parse_unit_pairs};
 use crate::document::Document;
+use crate::i18n::Language;
+use crate::metadata::{front_matter_range, metadata_entries};
 use crate::state::ServerState;
+use crate::utils::components::{scan_components, ComponentKind};
 use crate::utils::position::position_to_offset;
 
-/// Parse unit pairs from embedded data (format: "short = long")
-fn parse_unit_pairs(data: &'static str) -> Vec<(&'static str, &'static str)> {
-    data.lines()
-        .filter_map(|line| {
-            let trimmed = line.trim();
-            if trimmed.is_empty() || trimmed.starts_with('#') {
-                return None;
-            }
-            let mut parts = trimmed.split('=').map(|s| s.trim());
-            match (parts.next(), parts.next()) {
-                (Some(short), Some(long)) if parts.next().is_none() => Some((short, long)),
-                _ => None,
-            }
-        })
-        .collect()
-}
-
-/// Parse simple list from embedded data (one item per line)
-fn parse_simple_list(data: &'static str) -> Vec<&'static str> {
-    data.lines()
-        .map(|line| line.trim())
-        .filter(|line| !line.is_empty() && !line.starts_with('#'))
-        .collect()
-}
-
-/// Common cooking units (loaded from embedded data/units.txt)
-static UNITS: LazyLock<Vec<(&'static str, &'static str)>> =
-    LazyLock::new(|| parse_unit_pairs(include_str!("../data/units.txt")));
-
-/// Common time units (loaded from embedded data/time_units.txt)
-static TIME_UNITS: LazyLock<Vec<(&'static str, &'static str)>> =
-    LazyLock::new(|| parse_unit_pairs(include_str!("../data/time_units.txt")));
+/// Common timer durations (loaded from embedded data/timer_durations.txt)
+static TIMER_DURATIONS: LazyLock<Vec<&'static str>> =
+    LazyLock::new(|| parse_simple_list(include_str!("../data/timer_durations.txt")));
 
-/// Common cookware items (loaded from embedded data/cookware.txt)
-static COMMON_COOKWARE: LazyLock<Vec<&'static str>> =
-    LazyLock::new(|| parse_simple_list(include_str!("../data/cookware.txt")));
+/// Canonical metadata keys (loaded from embedded data/metadata_keys.txt)
+static METADATA_KEYS: LazyLock<Vec<(&'static str, &'static str)>> =
+    LazyLock::new(|| parse_unit_pairs(include_str!("../data/metadata_keys.txt")));
 
-/// Common ingredients for suggestions (loaded from embedded data/ingredients.txt)
-static COMMON_INGREDIENTS: LazyLock<Vec<&'static str>> =
-    LazyLock::new(|| parse_simple_list(include_str!("../data/ingredients.txt")));
+/// Suggested values for enumerated metadata keys (loaded from embedded
+/// data/metadata_values.txt)
+static METADATA_VALUES: LazyLock<Vec<(&'static str, &'static str)>> =
+    LazyLock::new(|| parse_unit_pairs(include_str!("../data/metadata_values.txt")));
 
 pub fn get_completions(
     doc: &Document,
@@ -63,10 +44,27 @@ pub fn get_completions(
 
     let items = match context {
         CompletionContext::Ingredient(prefix) => complete_ingredients(&prefix, doc, state),
-        CompletionContext::Cookware(prefix) => complete_cookware(&prefix, doc),
-        CompletionContext::Timer => complete_timer_units(),
-        CompletionContext::Unit(prefix) => complete_units(&prefix),
-        CompletionContext::Quantity => complete_quantity_snippets(),
+        CompletionContext::IngredientReference(prefix) => {
+            complete_defined_ingredients(&prefix, doc, offset)
+        }
+        CompletionContext::IngredientAlias { name, prefix } => {
+            complete_aliases(&name, &prefix, state)
+        }
+        CompletionContext::Cookware(prefix) => complete_cookware(&prefix, doc, state),
+        CompletionContext::Timer(prefix) => complete_timers(&prefix, doc, state),
+        CompletionContext::TimerDuration(name) => complete_timer_durations(name.as_deref(), state),
+        CompletionContext::TimerUnit(prefix) => {
+            complete_timer_units(&prefix, state.language_for(doc), state)
+        }
+        CompletionContext::Unit { prefix, ingredient } => complete_units(
+            &prefix,
+            ingredient.as_deref(),
+            state.language_for(doc),
+            state,
+        ),
+        CompletionContext::Quantity(ingredient) => {
+            complete_quantity_snippets(ingredient.as_deref(), state)
+        }
         CompletionContext::RecipeReference(prefix) => {
             if let Some(root) = workspace_root {
                 // Calculate the range from after '@' to cursor so the client
@@ -88,6 +86,19 @@ pub fn get_completions(
                 vec![]
             }
         }
+        // Keys and values may contain spaces, which the client treats as word
+        // boundaries, so replace the whole prefix explicitly.
+        CompletionContext::MetadataKey(prefix) => {
+            let range = prefix_range(doc, offset, &prefix, params);
+            complete_metadata_keys(&prefix, doc, range)
+        }
+        CompletionContext::MetadataValue { key, prefix } => {
+            let range = prefix_range(doc, offset, &prefix, params);
+            complete_metadata_values(&key, &prefix, doc, state, range)
+        }
+        CompletionContext::LineStart(prefix) => {
+            complete_line_start(offset - prefix.len(), offset, doc, state)
+        }
     };
 
     Some(CompletionResponse::List(CompletionList {
@@ -96,17 +107,137 @@ pub fn get_completions(
     }))
 }
 
+/// Range from the start of `prefix` (which ends at `offset`) to the cursor.
+fn prefix_range(doc: &Document, offset: usize, prefix: &str, params: &CompletionParams) -> Range {
+    let (line, utf8_col) = doc.line_index.line_col((offset - prefix.len()) as u32);
+    let utf16_col = doc.line_index.utf8_to_utf16_col(line, utf8_col);
+    Range {
+        start: Position {
+            line,
+            character: utf16_col,
+        },
+        end: params.text_document_position.position,
+    }
+}
+
 #[derive(Debug)]
 enum CompletionContext {
-    Ingredient(String),      // After @
-    Cookware(String),        // After #
-    Timer,                   // After ~
-    Unit(String),            // After % or in quantity
-    Quantity,                // Inside {} after number
-    RecipeReference(String), // After @. (file path reference)
+    Ingredient(String),            // After @
+    Cookware(String),              // After #
+    Timer(String),                 // After ~
+    TimerDuration(Option<String>), // Inside ~name{
+    TimerUnit(String),             // After % in a timer
+    Unit {
+        prefix: String,
+        ingredient: Option<String>,
+    }, // After % or in quantity
+    Quantity(Option<String>),      // Inside {} (of an ingredient)
+    RecipeReference(String),       // After @. (file path reference)
+    IngredientReference(String),   // After @& (defined earlier)
+    IngredientAlias {
+        name: String,
+        prefix: String,
+    }, // After @name|
+    MetadataKey(String),           // After >> or in front matter
+    MetadataValue {
+        key: String,
+        prefix: String,
+    }, // After `key:` in metadata
+    LineStart(String),             // A single word at the start of a line
+}
+
+/// Name of the ingredient whose `{` is at `brace`, if it is one (and not a
+/// recipe reference).
+fn ingredient_before_brace(chars: &[char], brace: usize) -> Option<String> {
+    let at = marker_before_brace(chars, brace)?;
+    if chars[at] != '@' {
+        return None;
+    }
+    let written: String = chars[at + 1..brace].iter().collect();
+    let (_, name) = split_modifiers(&written);
+    let name = name.split('|').next().unwrap_or(name).trim();
+    (!name.is_empty() && !name.starts_with('.')).then(|| name.to_string())
+}
+
+/// Position of the component marker (`@`, `#` or `~`) that the `{` at
+/// `brace` belongs to.
+fn marker_before_brace(chars: &[char], brace: usize) -> Option<usize> {
+    let at = chars[..brace]
+        .iter()
+        .rposition(|&c| matches!(c, '@' | '#' | '~' | '}' | '\n' | '\r'))?;
+    matches!(chars[at], '@' | '#' | '~').then_some(at)
+}
+
+/// Split the modifiers (`&`, `?`, `-`, `+`, and an intermediate reference
+/// like `&(=1)`) off the start of a component name prefix.
+fn split_modifiers(prefix: &str) -> (&str, &str) {
+    let mut end = 0;
+    let bytes = prefix.as_bytes();
+    while end < bytes.len() {
+        match bytes[end] {
+            b'&' | b'?' | b'-' | b'+' => end += 1,
+            b'(' if end > 0 && bytes[end - 1] == b'&' => match prefix[end..].find(')') {
+                Some(close) => end += close + 1,
+                None => break,
+            },
+            _ => break,
+        }
+    }
+    prefix.split_at(end)
+}
+
+/// Metadata context for the line the cursor is on: a `>>` line, or a line
+/// of the front matter.
+fn find_metadata_context(text: &str) -> Option<Option<CompletionContext>> {
+    let line_start = text.rfind('\n').map_or(0, |i| i + 1);
+    let line = &text[line_start..];
+
+    let entry = if let Some(rest) = line.trim_start().strip_prefix(">>") {
+        rest
+    } else if let Some(front_matter) = front_matter_range(text).filter(|r| r.end == text.len()) {
+        if line.starts_with(char::is_whitespace) {
+            // Only items of a YAML list (`tags:` followed by `  - quick`)
+            // are completed among nested values
+            let Some(item) = line.trim_start().strip_prefix('-') else {
+                return Some(None);
+            };
+            let key = text[front_matter.start..line_start]
+                .lines()
+                .rev()
+                .find(|l| !l.starts_with(char::is_whitespace))
+                .and_then(|l| l.split_once(':'))
+                .map(|(key, _)| key.trim().to_string());
+            return Some(key.map(|key| CompletionContext::MetadataValue {
+                key,
+                prefix: item.trim_start().to_string(),
+            }));
+        }
+        line
+    } else {
+        return None;
+    };
+
+    let context = match entry.split_once(':') {
+        None => CompletionContext::MetadataKey(entry.trim_start().to_string()),
+        // In a list (`tags: a, b` or `[a, b]`) only the last item is completed
+        Some((key, value)) => CompletionContext::MetadataValue {
+            key: key.trim().to_string(),
+            prefix: value
+                .rsplit([',', '['])
+                .next()
+                .unwrap_or(value)
+                .trim_start()
+                .to_string(),
+        },
+    };
+    Some(Some(context))
 }
 
 fn find_completion_context(text: &str) -> Option<CompletionContext> {
+    if let Some(context) = find_metadata_context(text) {
+        return context;
+    }
+
     // Limit backward scan to last 200 characters for performance
     const MAX_SCAN: usize = 200;
     let byte_start = text.len().saturating_sub(MAX_SCAN);
@@ -126,37 +257,59 @@ fn find_completion_context(text: &str) -> Option<CompletionContext> {
                 if !prefix.contains('}') {
                     if prefix.contains('{') {
                         // Inside braces - could be quantity context
-                        return Some(CompletionContext::Quantity);
+                        let brace = i + 1 + prefix.find('{').unwrap_or(0);
+                        return Some(CompletionContext::Quantity(ingredient_before_brace(
+                            &chars, brace,
+                        )));
                     }
                     let name_prefix = prefix.split('{').next().unwrap_or("").to_string();
                     // Check if this is a recipe/menu file reference (starts with ./ or ../)
                     if name_prefix.starts_with('.') {
                         return Some(CompletionContext::RecipeReference(name_prefix));
                     }
-                    return Some(CompletionContext::Ingredient(name_prefix));
+                    let (modifiers, name) = split_modifiers(&name_prefix);
+                    if let Some((name, alias)) = name.split_once('|') {
+                        return Some(CompletionContext::IngredientAlias {
+                            name: name.trim().to_string(),
+                            prefix: alias.to_string(),
+                        });
+                    }
+                    if modifiers.contains('&') {
+                        return Some(CompletionContext::IngredientReference(name.to_string()));
+                    }
+                    return Some(CompletionContext::Ingredient(name.to_string()));
                 }
                 return None;
             }
             '#' => {
                 let prefix: String = chars[i + 1..].iter().collect();
                 if !prefix.contains('}') {
-                    return Some(CompletionContext::Cookware(
-                        prefix.split('{').next().unwrap_or("").to_string(),
-                    ));
+                    let name_prefix = prefix.split('{').next().unwrap_or("");
+                    let (_, name) = split_modifiers(name_prefix);
+                    return Some(CompletionContext::Cookware(name.to_string()));
                 }
                 return None;
             }
             '~' => {
                 let rest: String = chars[i + 1..].iter().collect();
                 if !rest.contains('}') {
-                    return Some(CompletionContext::Timer);
+                    return Some(CompletionContext::Timer(rest));
                 }
                 return None;
             }
             '%' => {
                 let prefix: String = chars[i + 1..].iter().collect();
                 if !prefix.contains('}') {
-                    return Some(CompletionContext::Unit(prefix.trim().to_string()));
+                    let brace = chars[..i].iter().rposition(|&c| c == '{');
+                    let marker = brace.and_then(|brace| marker_before_brace(&chars, brace));
+                    if marker.is_some_and(|at| chars[at] == '~') {
+                        return Some(CompletionContext::TimerUnit(prefix.trim().to_string()));
+                    }
+                    let ingredient = brace.and_then(|brace| ingredient_before_brace(&chars, brace));
+                    return Some(CompletionContext::Unit {
+                        prefix: prefix.trim().to_string(),
+                        ingredient,
+                    });
                 }
                 return None;
             }
@@ -164,16 +317,25 @@ fn find_completion_context(text: &str) -> Option<CompletionContext> {
                 // Check if we're in an ingredient/cookware/timer context
                 for j in (0..i).rev() {
                     match chars[j] {
-                        '@' | '#' | '~' => {
+                        '~' => {
+                            let name: String = chars[j + 1..i].iter().collect();
+                            let name = name.trim();
+                            return Some(CompletionContext::TimerDuration(
+                                (!name.is_empty()).then(|| name.to_string()),
+                            ));
+                        }
+                        '@' | '#' => {
                             let inside: String = chars[i + 1..].iter().collect();
+                            let ingredient = ingredient_before_brace(&chars, i);
                             if inside.contains('%') {
                                 let after_percent: String =
                                     inside.split('%').next_back().unwrap_or("").to_string();
-                                return Some(CompletionContext::Unit(
-                                    after_percent.trim().to_string(),
-                                ));
+                                return Some(CompletionContext::Unit {
+                                    prefix: after_percent.trim().to_string(),
+                                    ingredient,
+                                });
                             }
-                            return Some(CompletionContext::Quantity);
+                            return Some(CompletionContext::Quantity(ingredient));
                         }
                         '\n' | '\r' => break,
                         _ => continue,
@@ -184,106 +346,376 @@ fn find_completion_context(text: &str) -> Option<CompletionContext> {
             _ => {}
         }
     }
-    None
+
+    let line = text.rsplit(['\n', '\r']).next().unwrap_or("");
+    line.chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, '=' | '-' | '[' | '_'))
+        .then(|| CompletionContext::LineStart(line.to_string()))
+}
+
+/// Where a suggestion comes from, best first. `sort_text` keeps this order
+/// so the client doesn't reshuffle the sources.
+#[derive(Debug, Clone, Copy)]
+enum Tier {
+    Recipe = 0,
+    Workspace = 1,
+    Aisle = 2,
+    Common = 3,
+}
+
+/// How well `name` matches what was typed, lower is better: a prefix of the
+/// name, a prefix of one of its words (`onio` in `red onion`), a substring,
+/// or the letters in order.
+fn match_score(prefix_lower: &str, name: &str) -> Option<u8> {
+    let name = name.to_lowercase();
+    if name.starts_with(prefix_lower) {
+        Some(0)
+    } else if name
+        .split(|c: char| c.is_whitespace() || c == '-')
+        .any(|word| word.starts_with(prefix_lower))
+    {
+        Some(1)
+    } else if name.contains(prefix_lower) {
+        Some(2)
+    } else if prefix_lower.chars().count() >= 2 && subsequence_match(prefix_lower, &name) {
+        Some(3)
+    } else {
+        None
+    }
+}
+
+/// Key under which case and simple plural variants (`Onion`, `onions`,
+/// `tomatoes`, `berries`) count as the same name.
+fn dedup_key(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    if let Some(stem) = name.strip_suffix("ies").filter(|s| s.len() > 1) {
+        format!("{}y", stem)
+    } else if ["oes", "ches", "shes", "xes", "sses"]
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+    {
+        name[..name.len() - 2].to_string()
+    } else if name.ends_with('s') && !name.ends_with("ss") && name.len() > 3 {
+        name[..name.len() - 1].to_string()
+    } else {
+        name
+    }
+}
+
+/// Matching, de-duplicated suggestions collected from several sources.
+struct Candidates {
+    prefix_lower: String,
+    seen: Vec<String>,
+    items: Vec<CompletionItem>,
+}
+
+impl Candidates {
+    fn new(prefix: &str) -> Self {
+        Self {
+            prefix_lower: prefix.to_lowercase(),
+            seen: Vec::new(),
+            items: Vec::new(),
+        }
+    }
+
+    /// Add the item for `name` unless it doesn't match or a variant of it
+    /// was already added (from a better source).
+    fn add(&mut self, name: &str, tier: Tier, item: impl FnOnce() -> CompletionItem) {
+        let Some(score) = match_score(&self.prefix_lower, name) else {
+            return;
+        };
+        let key = dedup_key(name);
+        if self.seen.contains(&key) {
+            return;
+        }
+        self.seen.push(key);
+
+        let mut item = item();
+        item.sort_text = Some(format!("{}{}{}", tier as u8, score, name.to_lowercase()));
+        item.filter_text = Some(name.to_string());
+        self.items.push(item);
+    }
+}
+
+fn ingredient_item(name: &str, detail: String) -> CompletionItem {
+    CompletionItem {
+        label: name.to_string(),
+        kind: Some(CompletionItemKind::VARIABLE),
+        detail: Some(detail),
+        insert_text: Some(format!("{}{{$0}}", name)),
+        insert_text_format: Some(InsertTextFormat::SNIPPET),
+        data: Some(resolve_data(name)),
+        ..Default::default()
+    }
 }
 
 fn complete_ingredients(prefix: &str, doc: &Document, state: &ServerState) -> Vec<CompletionItem> {
-    let mut items = Vec::new();
-    let prefix_lower = prefix.to_lowercase();
+    let mut candidates = Candidates::new(prefix);
+    let sources = state.completion_sources();
 
     // Add existing ingredients from current document (highest priority)
     if let Some(ref result) = doc.parse_result {
         for ingredient in &result.recipe.ingredients {
             let name = &ingredient.name;
-            if name.to_lowercase().starts_with(&prefix_lower) {
-                items.push(CompletionItem {
-                    label: name.clone(),
-                    kind: Some(CompletionItemKind::VARIABLE),
-                    detail: Some("Ingredient (from recipe)".into()),
-                    insert_text: Some(format!("{}{{$0}}", name)),
-                    insert_text_format: Some(InsertTextFormat::SNIPPET),
-                    ..Default::default()
-                });
-            }
+            candidates.add(name, Tier::Recipe, || {
+                ingredient_item(name, "Ingredient (from recipe)".into())
+            });
         }
     }
 
-    // Add from other open documents in workspace
-    for entry in state.documents.iter() {
-        if entry.key() == &doc.uri {
-            continue;
-        }
-        if let Some(ref result) = entry.value().parse_result {
-            for ingredient in &result.recipe.ingredients {
-                let name = &ingredient.name;
-                if name.to_lowercase().starts_with(&prefix_lower)
-                    && !items.iter().any(|i| &i.label == name)
-                {
-                    items.push(CompletionItem {
-                        label: name.clone(),
-                        kind: Some(CompletionItemKind::VARIABLE),
-                        detail: Some("Ingredient (from workspace)".into()),
-                        insert_text: Some(format!("{}{{$0}}", name)),
-                        insert_text_format: Some(InsertTextFormat::SNIPPET),
-                        ..Default::default()
+    if sources.workspace {
+        // Add from other open documents in workspace
+        for entry in state.documents.iter() {
+            if entry.key() == &doc.uri {
+                continue;
+            }
+            if let Some(ref result) = entry.value().parse_result {
+                for ingredient in &result.recipe.ingredients {
+                    let name = &ingredient.name;
+                    candidates.add(name, Tier::Workspace, || {
+                        ingredient_item(name, "Ingredient (from workspace)".into())
                     });
                 }
             }
         }
     }
 
-    // Add ingredients from aisle.conf (user's grocery list)
-    for aisle_ingredient in state.get_aisle_ingredients() {
-        if aisle_ingredient
-            .name
-            .to_lowercase()
-            .starts_with(&prefix_lower)
-            && !items.iter().any(|i| i.label == aisle_ingredient.name)
-        {
-            // Show alias info if this is not the common name
-            let detail = if aisle_ingredient.name != aisle_ingredient.common_name {
-                format!(
-                    "{} (alias for {})",
-                    aisle_ingredient.category, aisle_ingredient.common_name
-                )
-            } else {
-                aisle_ingredient.category.clone()
-            };
+    if sources.aisle {
+        // Add ingredients from aisle.conf (user's grocery list)
+        for aisle_ingredient in state.get_aisle_ingredients() {
+            candidates.add(&aisle_ingredient.name, Tier::Aisle, || {
+                // Show alias info if this is not the common name
+                let detail = if aisle_ingredient.name != aisle_ingredient.common_name {
+                    format!(
+                        "{} (alias for {})",
+                        aisle_ingredient.category, aisle_ingredient.common_name
+                    )
+                } else {
+                    aisle_ingredient.category.clone()
+                };
+                CompletionItem {
+                    documentation: Some(Documentation::String(format!(
+                        "From aisle.conf - {}",
+                        aisle_ingredient.category
+                    ))),
+                    ..ingredient_item(&aisle_ingredient.name, detail)
+                }
+            });
+        }
+    }
 
-            items.push(CompletionItem {
-                label: aisle_ingredient.name.clone(),
-                kind: Some(CompletionItemKind::VARIABLE),
-                detail: Some(detail),
-                documentation: Some(Documentation::String(format!(
-                    "From aisle.conf - {}",
-                    aisle_ingredient.category
-                ))),
-                insert_text: Some(format!("{}{{$0}}", aisle_ingredient.name)),
-                insert_text_format: Some(InsertTextFormat::SNIPPET),
-                ..Default::default()
+    if sources.common {
+        // Add common ingredients (lowest priority fallback)
+        let data = state.completion_data(state.language_for(doc));
+        for ingredient in &data.ingredients {
+            candidates.add(ingredient, Tier::Common, || {
+                ingredient_item(ingredient, "Common ingredient".into())
             });
         }
     }
 
-    // Add common ingredients (lowest priority fallback)
-    for &ingredient in COMMON_INGREDIENTS.iter() {
-        if ingredient.to_lowercase().starts_with(&prefix_lower)
-            && !items.iter().any(|i| i.label == ingredient)
+    candidates.items
+}
+
+/// Ingredients defined before `offset`, for a `@&` reference to point at.
+fn complete_defined_ingredients(
+    prefix: &str,
+    doc: &Document,
+    offset: usize,
+) -> Vec<CompletionItem> {
+    let prefix_lower = prefix.to_lowercase();
+    let mut items: Vec<CompletionItem> = Vec::new();
+
+    for component in scan_components(&doc.content) {
+        if component.span.end() > offset {
+            break;
+        }
+        let name = &component.name;
+        if component.kind != ComponentKind::Ingredient
+            || component.modifiers.is_reference()
+            || name.starts_with('.')
+            || !name.to_lowercase().starts_with(&prefix_lower)
+            || items.iter().any(|i| &i.label == name)
         {
-            items.push(CompletionItem {
-                label: ingredient.into(),
-                kind: Some(CompletionItemKind::VARIABLE),
-                detail: Some("Common ingredient".into()),
-                insert_text: Some(format!("{}{{$0}}", ingredient)),
-                insert_text_format: Some(InsertTextFormat::SNIPPET),
-                ..Default::default()
-            });
+            continue;
         }
+        items.push(CompletionItem {
+            label: name.clone(),
+            kind: Some(CompletionItemKind::REFERENCE),
+            detail: Some("Ingredient (defined earlier)".into()),
+            insert_text: Some(format!("{}{{$0}}", name)),
+            data: Some(resolve_data(name)),
+            insert_text_format: Some(InsertTextFormat::SNIPPET),
+            ..Default::default()
+        });
     }
 
     items
 }
 
+/// Other names for `name` in aisle.conf, to use as its display alias.
+fn complete_aliases(name: &str, prefix: &str, state: &ServerState) -> Vec<CompletionItem> {
+    let aisle = state.get_aisle_ingredients();
+    let Some(common_name) = aisle
+        .iter()
+        .find(|i| i.name.eq_ignore_ascii_case(name))
+        .map(|i| i.common_name.clone())
+    else {
+        return Vec::new();
+    };
+    let prefix_lower = prefix.to_lowercase();
+
+    aisle
+        .into_iter()
+        .filter(|i| {
+            i.common_name == common_name
+                && !i.name.eq_ignore_ascii_case(name)
+                && i.name.to_lowercase().starts_with(&prefix_lower)
+        })
+        .map(|i| CompletionItem {
+            label: i.name.clone(),
+            kind: Some(CompletionItemKind::VARIABLE),
+            detail: Some(format!("Alias for {} ({})", i.common_name, i.category)),
+            insert_text: Some(format!("{}{{$0}}", i.name)),
+            data: Some(resolve_data(&i.common_name)),
+            insert_text_format: Some(InsertTextFormat::SNIPPET),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Data attached to ingredient items so `completionItem/resolve` knows which
+/// ingredient to document.
+#[derive(Debug, Serialize, Deserialize)]
+struct ResolveData {
+    ingredient: String,
+}
+
+fn resolve_data(ingredient: &str) -> serde_json::Value {
+    serde_json::to_value(ResolveData {
+        ingredient: ingredient.to_string(),
+    })
+    .unwrap_or_default()
+}
+
+/// How a recipe is named in documentation: its path in the workspace without
+/// the extension, or its file name outside one
+fn recipe_name(uri: &Url, workspace_root: Option<&Path>) -> String {
+    let Ok(path) = uri.to_file_path() else {
+        return uri.to_string();
+    };
+    match workspace_root.and_then(|root| path.strip_prefix(root).ok()) {
+        Some(relative) => relative
+            .with_extension("")
+            .to_string_lossy()
+            .replace('\\', "/"),
+        None => path
+            .file_stem()
+            .map_or_else(|| uri.to_string(), |s| s.to_string_lossy().into_owned()),
+    }
+}
+
+/// Most recipes listed in an ingredient's documentation
+const MAX_RESOLVE_RECIPES: usize = 10;
+
+/// `completionItem/resolve`: attach documentation to an ingredient item —
+/// the recipes using it and how much, its aisle category and aliases, and
+/// whether the pantry has it. Computed here rather than for every item in
+/// the completion list.
+pub fn resolve_completion(
+    mut item: CompletionItem,
+    state: &ServerState,
+    workspace_root: Option<&Path>,
+) -> CompletionItem {
+    let Some(data) = item
+        .data
+        .clone()
+        .and_then(|data| serde_json::from_value::<ResolveData>(data).ok())
+    else {
+        return item;
+    };
+    let name = data.ingredient;
+    let mut sections = Vec::new();
+
+    // By recipe file: recipes in different directories may share a name
+    let mut recipes: Vec<(Url, String, Vec<String>)> = Vec::new();
+    for (uri, used) in state.ingredient_uses(&name) {
+        // Unmeasured uses still list the recipe
+        let quantity = match (used.value, used.unit) {
+            (Some(value), Some(unit)) => format!("{} {}", value, unit),
+            (Some(value), None) => value,
+            _ => String::new(),
+        };
+        match recipes.iter_mut().find(|(u, _, _)| *u == uri) {
+            Some((_, _, quantities)) => quantities.push(quantity),
+            None => {
+                let recipe = recipe_name(&uri, workspace_root);
+                recipes.push((uri, recipe, vec![quantity]));
+            }
+        }
+    }
+    if !recipes.is_empty() {
+        recipes.sort_by(|a, b| a.1.cmp(&b.1));
+        let total = recipes.len();
+        let mut lines: Vec<String> = recipes
+            .into_iter()
+            .take(MAX_RESOLVE_RECIPES)
+            .map(|(_, recipe, quantities)| {
+                let quantities: Vec<_> = quantities.into_iter().filter(|q| !q.is_empty()).collect();
+                if quantities.is_empty() {
+                    format!("- {}", recipe)
+                } else {
+                    format!("- {}: {}", recipe, quantities.join(", "))
+                }
+            })
+            .collect();
+        if total > MAX_RESOLVE_RECIPES {
+            lines.push(format!("- … and {} more", total - MAX_RESOLVE_RECIPES));
+        }
+        sections.push(format!("**Used in:**\n{}", lines.join("\n")));
+    }
+
+    let aisle = state.get_aisle_ingredients();
+    if let Some(entry) = aisle.iter().find(|i| i.name.eq_ignore_ascii_case(&name)) {
+        sections.push(format!("**Aisle:** {}", entry.category));
+        let aliases: Vec<_> = aisle
+            .iter()
+            .filter(|i| i.common_name == entry.common_name && !i.name.eq_ignore_ascii_case(&name))
+            .map(|i| i.name.as_str())
+            .collect();
+        if !aliases.is_empty() {
+            sections.push(format!("**Also known as:** {}", aliases.join(", ")));
+        }
+    }
+
+    if let Ok(guard) = state.pantry_config.read() {
+        if let Some(ref pantry) = *guard {
+            let status = match pantry.find_ingredient(&name) {
+                Some((section, item)) => {
+                    let mut status = match item.quantity() {
+                        Some(quantity) => format!("{} in {}", quantity, section),
+                        None => format!("in {}", section),
+                    };
+                    if item.is_low() {
+                        status.push_str(" (low)");
+                    }
+                    status
+                }
+                None => "not in stock".to_string(),
+            };
+            sections.push(format!("**Pantry:** {}", status));
+        }
+    }
+
+    if !sections.is_empty() {
+        item.documentation = Some(Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: sections.join("\n\n"),
+        }));
+    }
+    item
+}
+
 /// Scan a directory recursively for .cook and .menu files
 fn scan_recipe_files(root: &Path) -> Vec<(String, &'static str)> {
     let mut files = Vec::new();
@@ -411,49 +843,183 @@ fn complete_recipe_references(
         .collect()
 }
 
-fn complete_cookware(prefix: &str, doc: &Document) -> Vec<CompletionItem> {
-    let mut items = Vec::new();
-    let prefix_lower = prefix.to_lowercase();
+fn cookware_item(name: &str, detail: &str) -> CompletionItem {
+    CompletionItem {
+        label: name.to_string(),
+        kind: Some(CompletionItemKind::CLASS),
+        detail: Some(detail.to_string()),
+        insert_text: Some(format!("{}{{$0}}", name)),
+        insert_text_format: Some(InsertTextFormat::SNIPPET),
+        ..Default::default()
+    }
+}
+
+fn complete_cookware(prefix: &str, doc: &Document, state: &ServerState) -> Vec<CompletionItem> {
+    let mut candidates = Candidates::new(prefix);
 
     // Add existing cookware from document
     if let Some(ref result) = doc.parse_result {
         for cookware in &result.recipe.cookware {
             let name = &cookware.name;
-            if name.to_lowercase().starts_with(&prefix_lower) {
-                items.push(CompletionItem {
-                    label: name.clone(),
-                    kind: Some(CompletionItemKind::CLASS),
-                    detail: Some("Cookware (from recipe)".into()),
-                    insert_text: Some(format!("{}{{$0}}", name)),
-                    insert_text_format: Some(InsertTextFormat::SNIPPET),
-                    ..Default::default()
-                });
-            }
+            candidates.add(name, Tier::Recipe, || {
+                cookware_item(name, "Cookware (from recipe)")
+            });
         }
     }
 
-    // Add common cookware
-    for &cookware in COMMON_COOKWARE.iter() {
-        if cookware.to_lowercase().starts_with(&prefix_lower)
-            && !items.iter().any(|i| i.label == cookware)
-        {
+    if state.completion_sources().common {
+        // Add common cookware
+        let data = state.completion_data(state.language_for(doc));
+        for cookware in &data.cookware {
+            candidates.add(cookware, Tier::Common, || {
+                cookware_item(cookware, "Common cookware")
+            });
+        }
+    }
+
+    candidates.items
+}
+
+fn timer_item(name: &str, detail: String, duration: Option<&str>) -> CompletionItem {
+    let insert_text = match duration {
+        Some(duration) => format!("{}{{${{1:{}}}}}", name, duration),
+        None => format!("{}{{$1%${{2:minutes}}}}", name),
+    };
+    CompletionItem {
+        label: name.to_string(),
+        kind: Some(CompletionItemKind::EVENT),
+        detail: Some(detail),
+        insert_text: Some(insert_text),
+        insert_text_format: Some(InsertTextFormat::SNIPPET),
+        ..Default::default()
+    }
+}
+
+/// Named timers from the recipe and the workspace, each filled in with the
+/// duration it is most often set to. Before a name is typed, common
+/// durations are offered as unnamed timers (`~{10%minutes}`).
+fn complete_timers(prefix: &str, doc: &Document, state: &ServerState) -> Vec<CompletionItem> {
+    let workspace = state.timer_uses();
+    let usual_duration = |name: &str| {
+        let name = name.to_lowercase();
+        by_frequency(
+            workspace
+                .iter()
+                .filter(|u| u.name == name)
+                .filter_map(|u| u.quantity()),
+        )
+        .into_iter()
+        .next()
+        .map(|(duration, _)| duration)
+    };
+
+    let mut candidates = Candidates::new(prefix);
+    if let Some(ref result) = doc.parse_result {
+        for name in result.recipe.timers.iter().filter_map(|t| t.name.as_ref()) {
+            candidates.add(name, Tier::Recipe, || {
+                timer_item(
+                    name,
+                    "Timer (from recipe)".into(),
+                    usual_duration(name).as_deref(),
+                )
+            });
+        }
+    }
+    let workspace_names = if state.completion_sources().workspace {
+        by_frequency(workspace.iter().map(|u| u.name.clone()))
+    } else {
+        Vec::new()
+    };
+    for (name, count) in workspace_names {
+        candidates.add(&name, Tier::Workspace, || {
+            timer_item(
+                &name,
+                format!(
+                    "Timer used {} time{}",
+                    count,
+                    if count == 1 { "" } else { "s" }
+                ),
+                usual_duration(&name).as_deref(),
+            )
+        });
+    }
+
+    let mut items = candidates.items;
+    if prefix.is_empty() {
+        items.extend(TIMER_DURATIONS.iter().enumerate().map(|(i, duration)| {
+            let text = format!("{{{}}}", duration);
+            CompletionItem {
+                label: text.clone(),
+                kind: Some(CompletionItemKind::VALUE),
+                detail: Some("Unnamed timer".into()),
+                insert_text: Some(text),
+                sort_text: Some(format!("4{:02}", i)),
+                ..Default::default()
+            }
+        }));
+    }
+    items
+}
+
+/// Durations inside a timer's braces: the ones a named timer is set to
+/// elsewhere first, then common durations and ranges.
+fn complete_timer_durations(name: Option<&str>, state: &ServerState) -> Vec<CompletionItem> {
+    let mut items = Vec::new();
+
+    if let Some(name) = name.filter(|_| state.completion_sources().workspace) {
+        let name_lower = name.to_lowercase();
+        let used = by_frequency(
+            state
+                .timer_uses()
+                .into_iter()
+                .filter(|u| u.name == name_lower)
+                .filter_map(|u| u.quantity()),
+        );
+        for (rank, (duration, count)) in used.into_iter().take(MAX_USED_QUANTITIES).enumerate() {
             items.push(CompletionItem {
-                label: cookware.into(),
-                kind: Some(CompletionItemKind::CLASS),
-                detail: Some("Common cookware".into()),
-                insert_text: Some(format!("{}{{$0}}", cookware)),
-                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                label: duration.clone(),
+                kind: Some(CompletionItemKind::VALUE),
+                detail: Some(usage_detail(count, name)),
+                insert_text: Some(duration),
+                sort_text: Some(format!("0{:04}", rank)),
                 ..Default::default()
             });
         }
     }
 
+    for (i, duration) in TIMER_DURATIONS.iter().enumerate() {
+        if items.iter().any(|item| item.label == *duration) {
+            continue;
+        }
+        let detail = if duration.contains('-') {
+            "Duration range"
+        } else {
+            "Common duration"
+        };
+        items.push(CompletionItem {
+            label: duration.to_string(),
+            kind: Some(CompletionItemKind::VALUE),
+            detail: Some(detail.into()),
+            insert_text: Some(duration.to_string()),
+            sort_text: Some(format!("1{:02}", i)),
+            ..Default::default()
+        });
+    }
+
     items
 }
 
-fn complete_timer_units() -> Vec<CompletionItem> {
-    TIME_UNITS
+fn complete_timer_units(
+    prefix: &str,
+    language: Language,
+    state: &ServerState,
+) -> Vec<CompletionItem> {
+    let prefix_lower = prefix.to_lowercase();
+    state
+        .completion_data(language)
+        .time_units
         .iter()
+        .filter(|(short, _)| short.to_lowercase().starts_with(&prefix_lower))
         .map(|(short, long)| CompletionItem {
             label: short.to_string(),
             kind: Some(CompletionItemKind::UNIT),
@@ -464,29 +1030,88 @@ fn complete_timer_units() -> Vec<CompletionItem> {
         .collect()
 }
 
-fn complete_units(prefix: &str) -> Vec<CompletionItem> {
+/// How often each item occurs, most frequent first (ties alphabetically).
+fn by_frequency<T: Ord>(items: impl IntoIterator<Item = T>) -> Vec<(T, usize)> {
+    let mut counts: Vec<(T, usize)> = Vec::new();
+    for item in items {
+        match counts.iter_mut().find(|(t, _)| *t == item) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((item, 1)),
+        }
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+fn usage_detail(count: usize, ingredient: &str) -> String {
+    format!(
+        "Used {} time{} for {}",
+        count,
+        if count == 1 { "" } else { "s" },
+        ingredient
+    )
+}
+
+fn complete_units(
+    prefix: &str,
+    ingredient: Option<&str>,
+    language: Language,
+    state: &ServerState,
+) -> Vec<CompletionItem> {
     let prefix_lower = prefix.to_lowercase();
+    let sources = state.completion_sources();
+
+    // Units the workspace already uses for this ingredient come first
+    let mut items: Vec<CompletionItem> = Vec::new();
+    if let Some(ingredient) = ingredient.filter(|_| sources.workspace) {
+        let used = by_frequency(
+            state
+                .ingredient_uses(ingredient)
+                .into_iter()
+                .filter_map(|(_, u)| u.unit),
+        );
+        for (rank, (unit, count)) in used.into_iter().enumerate() {
+            if !unit.to_lowercase().starts_with(&prefix_lower) {
+                continue;
+            }
+            items.push(CompletionItem {
+                label: unit.clone(),
+                kind: Some(CompletionItemKind::UNIT),
+                detail: Some(usage_detail(count, ingredient)),
+                sort_text: Some(format!("0{:04}", rank)),
+                ..Default::default()
+            });
+        }
+    }
+    if !sources.common {
+        return items;
+    }
 
-    let mut items: Vec<_> = UNITS
-        .iter()
-        .filter(|(short, _)| short.to_lowercase().starts_with(&prefix_lower))
-        .map(|(short, long)| CompletionItem {
-            label: short.to_string(),
-            kind: Some(CompletionItemKind::UNIT),
-            detail: Some(long.to_string()),
-            ..Default::default()
-        })
-        .collect();
+    let data = state.completion_data(language);
+    for (short, long) in &data.units {
+        if short.to_lowercase().starts_with(&prefix_lower)
+            && !items.iter().any(|i| i.label == *short)
+        {
+            items.push(CompletionItem {
+                label: short.to_string(),
+                kind: Some(CompletionItemKind::UNIT),
+                detail: Some(long.to_string()),
+                sort_text: Some(format!("1{}", short)),
+                ..Default::default()
+            });
+        }
+    }
 
     // Also add time units when completing units
     items.extend(
-        TIME_UNITS
+        data.time_units
             .iter()
             .filter(|(short, _)| short.to_lowercase().starts_with(&prefix_lower))
             .map(|(short, long)| CompletionItem {
                 label: short.to_string(),
                 kind: Some(CompletionItemKind::UNIT),
                 detail: Some(format!("{} (time)", long)),
+                sort_text: Some(format!("2{}", short)),
                 ..Default::default()
             }),
     );
@@ -494,14 +1119,218 @@ fn complete_units(prefix: &str) -> Vec<CompletionItem> {
     items
 }
 
-fn complete_quantity_snippets() -> Vec<CompletionItem> {
-    vec![
+fn complete_metadata_keys(prefix: &str, doc: &Document, range: Range) -> Vec<CompletionItem> {
+    let prefix_lower = prefix.to_lowercase();
+    let canonical = |key: &str| StdKey::from_str(key).map_or(key.to_string(), |k| k.to_string());
+    let present: Vec<String> = metadata_entries(&doc.content)
+        .iter()
+        .map(|entry| canonical(&entry.key))
+        .collect();
+
+    METADATA_KEYS
+        .iter()
+        .filter(|(key, _)| key.starts_with(&prefix_lower))
+        .filter(|(key, _)| !present.contains(&canonical(key)))
+        .map(|(key, description)| CompletionItem {
+            label: key.to_string(),
+            kind: Some(CompletionItemKind::PROPERTY),
+            detail: Some(description.to_string()),
+            filter_text: Some(key.to_string()),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                range,
+                new_text: format!("{}: ", key),
+            })),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn complete_metadata_values(
+    key: &str,
+    prefix: &str,
+    doc: &Document,
+    state: &ServerState,
+    range: Range,
+) -> Vec<CompletionItem> {
+    // Accept aliases such as `category` for `course`
+    let key = StdKey::from_str(&key.to_lowercase()).map_or(key.to_lowercase(), |k| k.to_string());
+    let prefix_lower = prefix.to_lowercase();
+
+    if key == StdKey::Tags.as_ref() {
+        return complete_tags(&prefix_lower, doc, state, range);
+    }
+
+    METADATA_VALUES
+        .iter()
+        .filter(|(k, value)| *k == key && value.to_lowercase().starts_with(&prefix_lower))
+        .map(|(_, value)| CompletionItem {
+            label: value.to_string(),
+            kind: Some(CompletionItemKind::VALUE),
+            detail: Some(key.clone()),
+            filter_text: Some(value.to_string()),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                range,
+                new_text: value.to_string(),
+            })),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Tags used across the workspace, most used first, leaving out the ones
+/// this recipe already has.
+fn complete_tags(
+    prefix_lower: &str,
+    doc: &Document,
+    state: &ServerState,
+    range: Range,
+) -> Vec<CompletionItem> {
+    if !state.completion_sources().workspace {
+        return Vec::new();
+    }
+    let own_tags: Vec<String> = doc
+        .parse_result
+        .as_ref()
+        .and_then(|result| result.recipe.metadata.tags())
+        .unwrap_or_default()
+        .iter()
+        .map(|tag| tag.trim().to_string())
+        .collect();
+
+    let mut tags: Vec<(String, usize)> = state
+        .tag_index()
+        .into_iter()
+        .filter(|(tag, _)| tag.to_lowercase().starts_with(prefix_lower) && !own_tags.contains(tag))
+        .map(|(tag, uris)| (tag, uris.len()))
+        .collect();
+    tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    tags.into_iter()
+        .enumerate()
+        .map(|(rank, (tag, count))| CompletionItem {
+            label: tag.clone(),
+            kind: Some(CompletionItemKind::VALUE),
+            detail: Some(format!(
+                "Used in {} recipe{}",
+                count,
+                if count == 1 { "" } else { "s" }
+            )),
+            sort_text: Some(format!("{:05}", rank)),
+            filter_text: Some(tag.clone()),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                range,
+                new_text: tag,
+            })),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn snippet_item(label: &str, detail: &str, snippet: &str, sort: &str) -> CompletionItem {
+    CompletionItem {
+        label: label.to_string(),
+        kind: Some(CompletionItemKind::SNIPPET),
+        detail: Some(detail.to_string()),
+        insert_text: Some(snippet.to_string()),
+        insert_text_format: Some(InsertTextFormat::SNIPPET),
+        sort_text: Some(sort.to_string()),
+        ..Default::default()
+    }
+}
+
+/// Structure snippets for the line from `line_start` to the cursor at
+/// `offset`: a front matter block on the first line of a recipe without one,
+/// a section header and a block comment anywhere, and the recipe templates
+/// while the document is still empty.
+fn complete_line_start(
+    line_start: usize,
+    offset: usize,
+    doc: &Document,
+    state: &ServerState,
+) -> Vec<CompletionItem> {
+    let mut items = Vec::new();
+
+    if line_start == 0 && front_matter_range(&doc.content).is_none() {
+        items.push(snippet_item(
+            "front matter",
+            "YAML front matter with title, servings and tags",
+            "---\ntitle: ${1:Title}\nservings: ${2:4}\ntags: [$3]\n---\n\n$0",
+            "00",
+        ));
+    }
+    items.push(snippet_item(
+        "section",
+        "Section header (= Section =)",
+        "= ${1:Section} =\n\n$0",
+        "01",
+    ));
+    items.push(snippet_item(
+        "block comment",
+        "Block comment ([- ... -])",
+        "[- ${1:comment} -]$0",
+        "02",
+    ));
+
+    let empty =
+        doc.content[..line_start].trim().is_empty() && doc.content[offset..].trim().is_empty();
+    if empty {
+        if let Ok(templates) = state.templates.read() {
+            items.extend(templates.iter().map(|template| {
+                let mut item = snippet_item(
+                    &template.name,
+                    "Recipe template",
+                    &template.snippet(),
+                    &format!("1{}", template.name),
+                );
+                item.documentation = Some(Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: format!("```cooklang\n{}\n```", template.content.trim_end()),
+                }));
+                item
+            }));
+        }
+    }
+
+    items
+}
+
+/// Most quantities offered from earlier uses of an ingredient
+const MAX_USED_QUANTITIES: usize = 5;
+
+fn complete_quantity_snippets(
+    ingredient: Option<&str>,
+    state: &ServerState,
+) -> Vec<CompletionItem> {
+    let mut items = Vec::new();
+
+    // Quantities this ingredient was written with elsewhere, e.g. `200%g`
+    if let Some(ingredient) = ingredient.filter(|_| state.completion_sources().workspace) {
+        let used = by_frequency(
+            state
+                .ingredient_uses(ingredient)
+                .into_iter()
+                .filter_map(|(_, u)| u.quantity()),
+        );
+        for (rank, (quantity, count)) in used.into_iter().take(MAX_USED_QUANTITIES).enumerate() {
+            items.push(CompletionItem {
+                label: quantity.clone(),
+                kind: Some(CompletionItemKind::VALUE),
+                detail: Some(usage_detail(count, ingredient)),
+                insert_text: Some(quantity),
+                sort_text: Some(format!("0{:04}", rank)),
+                ..Default::default()
+            });
+        }
+    }
+
+    items.extend([
         CompletionItem {
             label: "quantity with unit".into(),
             kind: Some(CompletionItemKind::SNIPPET),
             insert_text: Some("${1:amount}%${2:unit}".into()),
             insert_text_format: Some(InsertTextFormat::SNIPPET),
             detail: Some("Insert quantity with unit".into()),
+            sort_text: Some("1".into()),
             ..Default::default()
         },
         CompletionItem {
@@ -510,14 +1339,18 @@ fn complete_quantity_snippets() -> Vec<CompletionItem> {
             insert_text: Some("${1:amount}".into()),
             insert_text_format: Some(InsertTextFormat::SNIPPET),
             detail: Some("Insert quantity without unit".into()),
+            sort_text: Some("2".into()),
             ..Default::default()
         },
-    ]
+    ]);
+
+    items
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state::AisleConfig;
     use std::fs;
     use tempfile::TempDir;
 
@@ -548,7 +1381,7 @@ mod tests {
     #[test]
     fn test_context_recipe_reference_with_brace_is_quantity() {
         let ctx = find_completion_context("@./sauces/Hollandaise{").unwrap();
-        assert!(matches!(ctx, CompletionContext::Quantity));
+        assert!(matches!(ctx, CompletionContext::Quantity(None)));
     }
 
     #[test]
@@ -557,6 +1390,362 @@ mod tests {
         assert!(matches!(ctx, CompletionContext::Ingredient(ref p) if p == "sal"));
     }
 
+    #[test]
+    fn test_context_metadata_line() {
+        let ctx = find_completion_context("Intro\n>> serv").unwrap();
+        assert!(matches!(ctx, CompletionContext::MetadataKey(ref p) if p == "serv"));
+
+        let ctx = find_completion_context(">> diet: veg").unwrap();
+        assert!(
+            matches!(ctx, CompletionContext::MetadataValue { ref key, ref prefix }
+            if key == "diet" && prefix == "veg")
+        );
+    }
+
+    #[test]
+    fn test_context_front_matter() {
+        let ctx = find_completion_context("---\ntitle: Soup\nco").unwrap();
+        assert!(matches!(ctx, CompletionContext::MetadataKey(ref p) if p == "co"));
+
+        let ctx = find_completion_context("---\ncourse: main c").unwrap();
+        assert!(
+            matches!(ctx, CompletionContext::MetadataValue { ref key, ref prefix }
+            if key == "course" && prefix == "main c")
+        );
+
+        // Nested mappings and text after the front matter are not completed
+        assert!(find_completion_context("---\ntime:\n  prep: 10").is_none());
+        let ctx = find_completion_context("---\ntitle: Soup\n---\nAdd @sal").unwrap();
+        assert!(matches!(ctx, CompletionContext::Ingredient(ref p) if p == "sal"));
+    }
+
+    #[test]
+    fn test_complete_metadata_keys_skips_present() {
+        let doc = Document::new(
+            tower_lsp::lsp_types::Url::parse("file:///test.cook").unwrap(),
+            1,
+            ">> serves: 2\n>> ".to_string(),
+        );
+        let items = complete_metadata_keys("", &doc, Range::default());
+        assert!(items.iter().any(|i| i.label == "prep time"));
+        // `serves` is an alias of `servings`
+        assert!(!items.iter().any(|i| i.label == "servings"));
+    }
+
+    #[test]
+    fn test_complete_metadata_values() {
+        let doc = Document::new(
+            tower_lsp::lsp_types::Url::parse("file:///test.cook").unwrap(),
+            1,
+            ">> course: d".to_string(),
+        );
+        let state = ServerState::new();
+        let items = complete_metadata_values("category", "d", &doc, &state, Range::default());
+        let labels: Vec<_> = items.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, vec!["dinner", "dessert", "drink"]);
+        assert!(complete_metadata_values("title", "", &doc, &state, Range::default()).is_empty());
+    }
+
+    #[test]
+    fn test_context_tags_list_item() {
+        let ctx = find_completion_context(">> tags: quick, ve").unwrap();
+        assert!(
+            matches!(ctx, CompletionContext::MetadataValue { ref key, ref prefix }
+            if key == "tags" && prefix == "ve")
+        );
+
+        let ctx = find_completion_context("---\ntags:\n  - quick\n  - ve").unwrap();
+        assert!(
+            matches!(ctx, CompletionContext::MetadataValue { ref key, ref prefix }
+            if key == "tags" && prefix == "ve")
+        );
+    }
+
+    #[test]
+    fn test_complete_tags_by_usage() {
+        let state = ServerState::new();
+        let uri =
+            |name: &str| tower_lsp::lsp_types::Url::parse(&format!("file:///{name}.cook")).unwrap();
+        state.open_document(uri("a"), 1, ">> tags: vegan, quick\nMix @a.".into());
+        state.open_document(uri("b"), 1, ">> tags: vegetarian, vegan\nMix @b.".into());
+        state.open_document(uri("c"), 1, ">> tags: quick, ve\nMix @c.".into());
+
+        let doc = state.get_document(&uri("c")).unwrap();
+        let items = complete_metadata_values("tags", "ve", &doc, &state, Range::default());
+        let labels: Vec<_> = items.iter().map(|i| i.label.as_str()).collect();
+        // The partially typed tag and the ones already on the recipe are left out
+        assert_eq!(labels, vec!["vegan", "vegetarian"]);
+        assert_eq!(items[0].detail.as_deref(), Some("Used in 2 recipes"));
+    }
+
+    #[test]
+    fn test_context_modifiers() {
+        for (text, expected) in [("@?sal", "sal"), ("@-oi", "oi"), ("@+flo", "flo")] {
+            let ctx = find_completion_context(text).unwrap();
+            assert!(
+                matches!(ctx, CompletionContext::Ingredient(ref p) if p == expected),
+                "{text}: {ctx:?}"
+            );
+        }
+
+        let ctx = find_completion_context("@&fl").unwrap();
+        assert!(matches!(ctx, CompletionContext::IngredientReference(ref p) if p == "fl"));
+        let ctx = find_completion_context("@&(=1)fl").unwrap();
+        assert!(matches!(ctx, CompletionContext::IngredientReference(ref p) if p == "fl"));
+
+        let ctx = find_completion_context("@onion|ye").unwrap();
+        assert!(
+            matches!(ctx, CompletionContext::IngredientAlias { ref name, ref prefix }
+            if name == "onion" && prefix == "ye")
+        );
+
+        let ctx = find_completion_context("#&po").unwrap();
+        assert!(matches!(ctx, CompletionContext::Cookware(ref p) if p == "po"));
+    }
+
+    #[test]
+    fn test_complete_defined_ingredients() {
+        let content = "Mix @flour{200%g} and @salt. Add @&f";
+        let doc = Document::new(
+            tower_lsp::lsp_types::Url::parse("file:///test.cook").unwrap(),
+            1,
+            content.to_string(),
+        );
+        let items = complete_defined_ingredients("", &doc, content.len());
+        let labels: Vec<_> = items.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, vec!["flour", "salt"]);
+
+        // Only ingredients before the cursor
+        let items = complete_defined_ingredients("", &doc, content.find("and").unwrap());
+        assert_eq!(items.len(), 1);
+    }
+
+    #[test]
+    fn test_complete_aliases() {
+        let state = ServerState::new();
+        *state.aisle_config.write().unwrap() =
+            AisleConfig::parse("[produce]\nonion|yellow onion|white onion\n");
+
+        let items = complete_aliases("onion", "ye", &state);
+        let labels: Vec<_> = items.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, vec!["yellow onion"]);
+
+        let items = complete_aliases("white onion", "", &state);
+        let labels: Vec<_> = items.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, vec!["onion", "yellow onion"]);
+
+        assert!(complete_aliases("garlic", "", &state).is_empty());
+    }
+
+    #[test]
+    fn test_context_unit_knows_ingredient() {
+        let ctx = find_completion_context("Add @?plain flour{200%").unwrap();
+        assert!(
+            matches!(ctx, CompletionContext::Unit { ref prefix, ref ingredient }
+            if prefix.is_empty() && ingredient.as_deref() == Some("plain flour"))
+        );
+
+        let ctx = find_completion_context("Add @eggs{").unwrap();
+        assert!(matches!(ctx, CompletionContext::Quantity(Some(ref i)) if i == "eggs"));
+
+        let ctx = find_completion_context("Use #pan{2%").unwrap();
+        assert!(
+            matches!(ctx, CompletionContext::Unit { ref ingredient, .. } if ingredient.is_none())
+        );
+    }
+
+    #[test]
+    fn test_context_timers() {
+        let ctx = find_completion_context("Let it ~re").unwrap();
+        assert!(matches!(ctx, CompletionContext::Timer(ref p) if p == "re"));
+
+        let ctx = find_completion_context("Let it ~rest{").unwrap();
+        assert!(matches!(ctx, CompletionContext::TimerDuration(Some(ref n)) if n == "rest"));
+
+        let ctx = find_completion_context("Bake ~{").unwrap();
+        assert!(matches!(ctx, CompletionContext::TimerDuration(None)));
+
+        let ctx = find_completion_context("Bake ~{25%mi").unwrap();
+        assert!(matches!(ctx, CompletionContext::TimerUnit(ref p) if p == "mi"));
+    }
+
+    #[test]
+    fn test_complete_timers() {
+        let state = ServerState::new();
+        let uri =
+            |name: &str| tower_lsp::lsp_types::Url::parse(&format!("file:///{name}.cook")).unwrap();
+        state.open_document(uri("a"), 1, "Let it ~rest{10%minutes}.".into());
+        state.open_document(uri("b"), 1, "~proof{1%hour}, ~rest{10%minutes}.".into());
+        let doc = Document::new(uri("c"), 1, "Then ~rest{5%min}.".into());
+
+        let items = complete_timers("", &doc, &state);
+        let labels: Vec<_> = items.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels[..2], ["rest", "proof"]);
+        assert!(labels.contains(&"{25-30%minutes}"));
+        assert_eq!(items[1].insert_text.as_deref(), Some("proof{${1:1%hour}}"));
+
+        // Once a name is typed, only names are offered
+        let items = complete_timers("pr", &doc, &state);
+        let labels: Vec<_> = items.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, ["proof"]);
+
+        let items = complete_timer_durations(Some("rest"), &state);
+        assert_eq!(items[0].label, "10%minutes");
+        assert_eq!(items[0].detail.as_deref(), Some("Used 2 times for rest"));
+        assert_eq!(items.iter().filter(|i| i.label == "10%minutes").count(), 1);
+        assert!(items.iter().any(|i| i.label == "25-30%minutes"));
+
+        let labels: Vec<_> = complete_timer_units("h", Language::English, &state)
+            .into_iter()
+            .map(|i| i.label)
+            .collect();
+        assert_eq!(labels, ["h", "hr", "hrs", "hour", "hours"]);
+    }
+
+    #[test]
+    fn test_context_line_start() {
+        assert!(
+            matches!(find_completion_context("Mix well.\nsec"), Some(CompletionContext::LineStart(ref p)) if p == "sec")
+        );
+        assert!(matches!(
+            find_completion_context(""),
+            Some(CompletionContext::LineStart(ref p)) if p.is_empty()
+        ));
+        assert!(find_completion_context("Mix well").is_none());
+    }
+
+    #[test]
+    fn test_complete_line_start() {
+        let state = ServerState::new();
+        let uri = tower_lsp::lsp_types::Url::parse("file:///new.cook").unwrap();
+        let labels = |doc: &Document, line_start: usize, offset: usize| -> Vec<String> {
+            complete_line_start(line_start, offset, doc, &state)
+                .into_iter()
+                .map(|i| i.label)
+                .collect()
+        };
+
+        let doc = Document::new(uri.clone(), 1, "ba".into());
+        assert_eq!(
+            labels(&doc, 0, 2),
+            ["front matter", "section", "block comment"]
+        );
+
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("bake.cook"), "Bake @dough{}.").unwrap();
+        state.load_templates(Some(dir.path()));
+        assert_eq!(
+            labels(&doc, 0, 2),
+            ["front matter", "section", "block comment", "bake"]
+        );
+
+        let doc = Document::new(uri.clone(), 1, ">> servings: 2\n\nMix @a.".into());
+        assert_eq!(labels(&doc, 16, 16), ["section", "block comment"]);
+
+        let doc = Document::new(uri, 1, "---\ntitle: A\n---\n".into());
+        assert_eq!(labels(&doc, 0, 0), ["section", "block comment"]);
+    }
+
+    #[test]
+    fn test_units_ranked_by_workspace_usage() {
+        let state = ServerState::new();
+        let uri =
+            |name: &str| tower_lsp::lsp_types::Url::parse(&format!("file:///{name}.cook")).unwrap();
+        state.open_document(uri("a"), 1, "@flour{1%cup} @eggs{3}".into());
+        state.open_document(uri("b"), 1, "@flour{200%g} @eggs{2%large}".into());
+        state.open_document(uri("c"), 1, "@flour{250%g} @eggs{3}".into());
+
+        let items = complete_units("", Some("flour"), Language::English, &state);
+        assert_eq!(items[0].label, "g");
+        assert_eq!(items[0].detail.as_deref(), Some("Used 2 times for flour"));
+        assert_eq!(items[1].label, "cup");
+        // Listed once, among the workspace units
+        assert_eq!(items.iter().filter(|i| i.label == "g").count(), 1);
+
+        let items = complete_units("", Some("eggs"), Language::English, &state);
+        assert_eq!(items[0].label, "large");
+
+        let items = complete_quantity_snippets(Some("eggs"), &state);
+        let labels: Vec<_> = items.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels[..2], ["3", "2%large"]);
+    }
+
+    #[test]
+    fn test_resolve_documents_ingredient() {
+        let dir = TempDir::new().unwrap();
+        let state = ServerState::new();
+        *state.aisle_config.write().unwrap() =
+            AisleConfig::parse("[produce]\nonion|yellow onion\n");
+        *state.pantry_config.write().unwrap() =
+            crate::state::parse_pantry_config("[pantry]\nonion = \"3\"\n");
+        let uri =
+            |name: &str| tower_lsp::lsp_types::Url::from_file_path(dir.path().join(name)).unwrap();
+        state.open_document(uri("Soup.cook"), 1, "@onion{2} and @onion{1%large}".into());
+        state.open_document(uri("Salad.cook"), 1, "@onion".into());
+        state.open_document(uri("mains/Salad.cook"), 1, "@onion{1}".into());
+
+        let doc = state.get_document(&uri("Soup.cook")).unwrap();
+        let items = complete_ingredients("oni", &doc, &state);
+        let item = items.into_iter().find(|i| i.label == "onion").unwrap();
+        // Documentation is only attached on resolve
+        assert!(item.documentation.is_none());
+
+        let resolved = resolve_completion(item, &state, Some(dir.path()));
+        let Some(Documentation::MarkupContent(docs)) = resolved.documentation else {
+            panic!("expected markdown documentation");
+        };
+        assert!(
+            docs.value
+                .contains("- Salad\n- Soup: 2, 1 large\n- mains/Salad: 1"),
+            "{}",
+            docs.value
+        );
+        assert!(docs.value.contains("**Aisle:** produce"));
+        assert!(docs.value.contains("**Also known as:** yellow onion"));
+        assert!(docs.value.contains("**Pantry:** 3 in pantry"));
+    }
+
+    #[test]
+    fn test_match_score() {
+        assert_eq!(match_score("", "salt"), Some(0));
+        assert_eq!(match_score("oni", "Onion"), Some(0));
+        assert_eq!(match_score("onio", "red onion"), Some(1));
+        assert_eq!(match_score("dried", "sun-dried tomatoes"), Some(1));
+        assert_eq!(match_score("nio", "onion"), Some(2));
+        assert_eq!(match_score("rdo", "red onion"), Some(3));
+        assert_eq!(match_score("xyz", "onion"), None);
+    }
+
+    #[test]
+    fn test_dedup_key_variants() {
+        assert_eq!(dedup_key("Onions"), dedup_key("onion"));
+        assert_eq!(dedup_key("tomatoes"), dedup_key("Tomato"));
+        assert_eq!(dedup_key("berries"), dedup_key("berry"));
+        assert_eq!(dedup_key("peaches"), dedup_key("peach"));
+        assert_ne!(dedup_key("glass"), dedup_key("glas"));
+    }
+
+    #[test]
+    fn test_ingredients_ranked_by_source_and_deduplicated() {
+        let state = ServerState::new();
+        *state.aisle_config.write().unwrap() = AisleConfig::parse("[produce]\nred onion\nonions\n");
+        let uri = tower_lsp::lsp_types::Url::parse("file:///test.cook").unwrap();
+        state.open_document(uri.clone(), 1, "Chop @Onion{1}.".into());
+
+        let doc = state.get_document(&uri).unwrap();
+        let items = complete_ingredients("onio", &doc, &state);
+        let mut sorted: Vec<_> = items.iter().collect();
+        sorted.sort_by_key(|i| i.sort_text.clone());
+        let labels: Vec<_> = sorted.iter().map(|i| i.label.as_str()).collect();
+
+        // The recipe's `Onion` wins over aisle `onions` and common `onion`;
+        // `red onion` matches on its second word.
+        assert_eq!(labels[0], "Onion");
+        assert_eq!(labels[1], "red onion");
+        assert!(!labels.contains(&"onions"));
+        assert!(!labels.contains(&"onion"));
+    }
+
     #[test]
     fn test_scan_recipe_files() {
         let dir = TempDir::new().unwrap();
@@ -0,0 +1,212 @@
+//! Completion data: common ingredients, cookware and units.
+//!
+//! The built-in lists are embedded from `data/`. A workspace can extend them
+//! with files of the same name and format in its data directory
+//! (`.cooklang/` unless configured otherwise): `ingredients.txt`,
+//! `cookware.txt`, `units.txt`, `time_units.txt`. A file containing a
+//! `!replace` line replaces the built-in list instead.
+//!
+//! German, French and Spanish recipes get their own ingredient and cookware
+//! lists (`data/<language>/`), and their units are offered before the
+//! built-in ones. Time units stay English, as the parser only understands
+//! those.
+
+use std::path::Path;
+
+use crate::i18n::Language;
+
+/// Directory, relative to the workspace root, holding data overrides when
+/// the settings don't name another one
+pub const DATA_DIR: &str = ".cooklang";
+
+const INGREDIENTS_FILE: &str = "ingredients.txt";
+const COOKWARE_FILE: &str = "cookware.txt";
+const UNITS_FILE: &str = "units.txt";
+const TIME_UNITS_FILE: &str = "time_units.txt";
+
+/// Marks an override file that replaces the built-in list
+const REPLACE_DIRECTIVE: &str = "!replace";
+
+/// Parse unit pairs from data (format: "short = long")
+pub fn parse_unit_pairs(data: &str) -> Vec<(&str, &str)> {
+    data.lines()
+        .filter_map(|line| {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                return None;
+            }
+            let mut parts = trimmed.split('=').map(|s| s.trim());
+            match (parts.next(), parts.next()) {
+                (Some(short), Some(long)) if parts.next().is_none() => Some((short, long)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Parse simple list from data (one item per line)
+pub fn parse_simple_list(data: &str) -> Vec<&str> {
+    data.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && *line != REPLACE_DIRECTIVE)
+        .collect()
+}
+
+/// Lists offered by completion
+#[derive(Debug, Clone)]
+pub struct CompletionData {
+    pub ingredients: Vec<String>,
+    pub cookware: Vec<String>,
+    /// Unit pairs (short, long)
+    pub units: Vec<(String, String)>,
+    /// Time unit pairs (short, long)
+    pub time_units: Vec<(String, String)>,
+}
+
+impl CompletionData {
+    /// The built-in lists for `language`
+    pub fn builtin(language: Language) -> Self {
+        let mut data = Self {
+            ingredients: owned_list(include_str!("../data/ingredients.txt")),
+            cookware: owned_list(include_str!("../data/cookware.txt")),
+            units: owned_pairs(include_str!("../data/units.txt")),
+            time_units: owned_pairs(include_str!("../data/time_units.txt")),
+        };
+
+        let localized = match language {
+            Language::English => None,
+            Language::German => Some((
+                include_str!("../data/de/ingredients.txt"),
+                include_str!("../data/de/cookware.txt"),
+                include_str!("../data/de/units.txt"),
+            )),
+            Language::French => Some((
+                include_str!("../data/fr/ingredients.txt"),
+                include_str!("../data/fr/cookware.txt"),
+                include_str!("../data/fr/units.txt"),
+            )),
+            Language::Spanish => Some((
+                include_str!("../data/es/ingredients.txt"),
+                include_str!("../data/es/cookware.txt"),
+                include_str!("../data/es/units.txt"),
+            )),
+        };
+        if let Some((ingredients, cookware, units)) = localized {
+            data.ingredients = owned_list(ingredients);
+            data.cookware = owned_list(cookware);
+            merge(&mut data.units, owned_pairs(units), false);
+        }
+        data
+    }
+
+    /// The built-in lists for `language` merged with the override files in
+    /// `dir`
+    pub fn load(dir: Option<&Path>, language: Language) -> Self {
+        let mut data = Self::builtin(language);
+        let Some(dir) = dir else {
+            return data;
+        };
+        let read = |name: &str| {
+            let path = dir.join(name);
+            let content = std::fs::read_to_string(&path).ok()?;
+            tracing::info!("Loading completion data from {:?}", path);
+            let replace = content.lines().any(|l| l.trim() == REPLACE_DIRECTIVE);
+            Some((content, replace))
+        };
+
+        if let Some((content, replace)) = read(INGREDIENTS_FILE) {
+            merge(&mut data.ingredients, owned_list(&content), replace);
+        }
+        if let Some((content, replace)) = read(COOKWARE_FILE) {
+            merge(&mut data.cookware, owned_list(&content), replace);
+        }
+        if let Some((content, replace)) = read(UNITS_FILE) {
+            merge(&mut data.units, owned_pairs(&content), replace);
+        }
+        if let Some((content, replace)) = read(TIME_UNITS_FILE) {
+            merge(&mut data.time_units, owned_pairs(&content), replace);
+        }
+        data
+    }
+}
+
+/// Whether `path` is one of the override files read by
+/// [`CompletionData::load`] from `dir`
+pub fn is_data_file(path: &Path, dir: &Path) -> bool {
+    path.parent() == Some(dir)
+        && path.file_name().and_then(|n| n.to_str()).is_some_and(|n| {
+            [INGREDIENTS_FILE, COOKWARE_FILE, UNITS_FILE, TIME_UNITS_FILE].contains(&n)
+        })
+}
+
+fn owned_list(data: &str) -> Vec<String> {
+    parse_simple_list(data)
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+}
+
+fn owned_pairs(data: &str) -> Vec<(String, String)> {
+    parse_unit_pairs(data)
+        .into_iter()
+        .map(|(short, long)| (short.to_string(), long.to_string()))
+        .collect()
+}
+
+/// Workspace entries come first so they rank above built-in ones.
+fn merge<T: PartialEq>(list: &mut Vec<T>, extra: Vec<T>, replace: bool) {
+    if replace {
+        list.clear();
+    }
+    list.retain(|item| !extra.contains(item));
+    list.splice(0..0, extra);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn workspace_files_extend_or_replace() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("cookware.txt"),
+            "# Our kitchen\ncombi oven\nPacojet\npot\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("units.txt"), "!replace\nhp = hotel pan\n").unwrap();
+
+        let builtin = CompletionData::builtin(Language::English);
+        let data = CompletionData::load(Some(dir.path()), Language::English);
+        assert_eq!(data.cookware[..3], ["combi oven", "Pacojet", "pot"]);
+        assert_eq!(data.cookware.len(), builtin.cookware.len() + 2);
+        assert_eq!(
+            data.units,
+            vec![("hp".to_string(), "hotel pan".to_string())]
+        );
+        assert_eq!(data.ingredients, builtin.ingredients);
+    }
+
+    #[test]
+    fn localized_lists() {
+        let data = CompletionData::builtin(Language::German);
+        assert!(data.ingredients.iter().any(|i| i == "Mehl"));
+        assert!(!data.ingredients.iter().any(|i| i == "flour"));
+        assert_eq!(data.units[0], ("EL".to_string(), "Esslöffel".to_string()));
+        assert!(data.units.iter().any(|(short, _)| short == "g"));
+        assert_eq!(
+            data.time_units,
+            CompletionData::builtin(Language::English).time_units
+        );
+    }
+
+    #[test]
+    fn recognizes_data_files() {
+        let dir = Path::new("/recipes/.cooklang");
+        assert!(is_data_file(&dir.join("units.txt"), dir));
+        assert!(!is_data_file(&dir.join("notes.txt"), dir));
+        assert!(!is_data_file(Path::new("/recipes/units.txt"), dir));
+    }
+}
@@ -1,24 +1,94 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+
 use cooklang::error::{Severity, SourceDiag};
-use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity};
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DocumentDiagnosticReportKind,
+    FullDocumentDiagnosticReport, Location, PreviousResultId, UnchangedDocumentDiagnosticReport,
+    Url, WorkspaceDiagnosticReport, WorkspaceDocumentDiagnosticReport,
+    WorkspaceFullDocumentDiagnosticReport, WorkspaceUnchangedDocumentDiagnosticReport,
+};
 
 use crate::document::Document;
+use crate::i18n::{fill, Strings};
+use crate::lints;
+use crate::metadata;
+use crate::portability;
+use crate::quick_fixes;
+use crate::references;
+use crate::settings::LintLevel;
+use crate::state::ServerState;
 use crate::utils::position::span_to_range;
 
-pub fn get_diagnostics(doc: &Document) -> Vec<Diagnostic> {
+/// Diagnostics for `doc`. Messages from the parser are in English; the
+/// server's own are in the document's language.
+pub fn get_diagnostics(
+    doc: &Document,
+    workspace_root: Option<&Path>,
+    state: &ServerState,
+) -> Vec<Diagnostic> {
     let mut diagnostics = Vec::new();
+    let strings = state.language_for(doc).strings();
+    let lints = state.settings().lints;
+
+    // Mistakes the parser reads as text; a parser diagnostic pointing into
+    // one (e.g. a timer with no duration for `~rest{10%min`) is superseded
+    let fixes = quick_fixes::find(doc, state, strings);
+    let superseded = |diag: &SourceDiag| {
+        diag.labels.first().is_some_and(|(span, _)| {
+            fixes
+                .iter()
+                .any(|fix| fix.span.contains(&span.start()) || fix.span.end == span.start())
+        })
+    };
 
     // Always use document-level errors/warnings (available even when parse fails)
-    for error in &doc.parse_errors {
-        if let Some(diag) = convert_source_diag(error, &doc.line_index) {
+    for error in doc.parse_errors.iter().filter(|e| !superseded(e)) {
+        if let Some(diag) = convert_source_diag(error, doc) {
             diagnostics.push(diag);
         }
     }
 
+    let metadata_diagnostics = if lints.metadata.is_enabled() {
+        metadata::validate(doc, strings)
+    } else {
+        Vec::new()
+    };
     for warning in &doc.parse_warnings {
-        if let Some(diag) = convert_source_diag(warning, &doc.line_index) {
+        // Superseded by the more specific metadata diagnostics below, if it
+        // points into the same entry (its label may be empty)
+        let about_entry = warning.labels.first().is_some_and(|(span, _)| {
+            metadata_diagnostics.iter().any(|(entry, _)| {
+                entry.contains(&span.start())
+                    || (span.start() < entry.end && entry.start < span.end())
+            })
+        });
+        if about_entry || superseded(warning) {
+            continue;
+        }
+        if let Some(diag) = convert_source_diag(warning, doc) {
             diagnostics.push(diag);
         }
     }
+    diagnostics.extend(fixes.iter().map(|fix| fix.diagnostic(doc)));
+
+    diagnostics.extend(with_level(
+        metadata_diagnostics.into_iter().map(|(_, diag)| diag),
+        lints.metadata,
+    ));
+    if lints.portability.is_enabled() {
+        diagnostics.extend(with_level(
+            portability::diagnostics(doc, strings),
+            lints.portability,
+        ));
+    }
+    if lints.references.is_enabled() {
+        diagnostics.extend(with_level(
+            reference_diagnostics(doc, workspace_root, strings),
+            lints.references,
+        ));
+    }
+    diagnostics.extend(lints::check(doc, &lints, strings));
 
     // If no parse result and no specific errors, show a generic message
     if doc.parse_result.is_none() && diagnostics.is_empty() {
@@ -26,7 +96,7 @@ pub fn get_diagnostics(doc: &Document) -> Vec<Diagnostic> {
             range: tower_lsp::lsp_types::Range::default(),
             severity: Some(DiagnosticSeverity::ERROR),
             source: Some("cooklang".into()),
-            message: "Failed to parse recipe".into(),
+            message: strings.failed_to_parse.into(),
             ..Default::default()
         });
     }
@@ -34,15 +104,122 @@ pub fn get_diagnostics(doc: &Document) -> Vec<Diagnostic> {
     diagnostics
 }
 
-fn convert_source_diag(
-    diag: &SourceDiag,
-    line_index: &crate::utils::line_index::LineIndex,
-) -> Option<Diagnostic> {
-    // Get the primary span from the first label
+/// Identifies a set of diagnostics: the same diagnostics always get the
+/// same id, so a client pulling them again can be told nothing changed
+pub fn result_id(diagnostics: &[Diagnostic]) -> String {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(diagnostics)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// A pulled report of `diagnostics`: unchanged if the client already has
+/// them as `previous_result_id`
+pub fn report(
+    diagnostics: Vec<Diagnostic>,
+    previous_result_id: Option<&str>,
+) -> DocumentDiagnosticReportKind {
+    let result_id = result_id(&diagnostics);
+    if previous_result_id == Some(result_id.as_str()) {
+        return DocumentDiagnosticReportKind::Unchanged(UnchangedDocumentDiagnosticReport {
+            result_id,
+        });
+    }
+    DocumentDiagnosticReportKind::Full(FullDocumentDiagnosticReport {
+        result_id: Some(result_id),
+        items: diagnostics,
+    })
+}
+
+/// Diagnostics for the recipe at `uri`, open or not: the open document and
+/// its version, or the file on disk
+pub fn recipe_diagnostics(
+    uri: &Url,
+    workspace_root: Option<&Path>,
+    state: &ServerState,
+) -> Option<(Vec<Diagnostic>, Option<i64>)> {
+    if let Some(doc) = state.get_document(uri) {
+        let diagnostics = get_diagnostics(&doc, workspace_root, state);
+        return Some((diagnostics, Some(doc.version as i64)));
+    }
+    let content = state.read_source(uri)?;
+    let extensions = state.settings().extensions();
+    let doc = Document::with_extensions(uri.clone(), 0, content, extensions);
+    Some((get_diagnostics(&doc, workspace_root, state), None))
+}
+
+/// `workspace/diagnostic`: every indexed recipe, open ones as they are in the
+/// editor and the others as they are on disk. Recipes whose diagnostics
+/// match the `previous` result the client has are reported unchanged.
+///
+/// Yields after each recipe, so a cancelled request stops there.
+pub async fn workspace_report(
+    state: &ServerState,
+    workspace_root: Option<&Path>,
+    previous: &[PreviousResultId],
+) -> WorkspaceDiagnosticReport {
+    let mut uris: Vec<Url> = state.index.iter().map(|e| e.key().clone()).collect();
+    uris.sort();
+
+    let mut items = Vec::new();
+    for uri in uris {
+        let root = workspace_root
+            .map(Path::to_path_buf)
+            .or_else(|| references::document_dir(&uri));
+        let Some((diagnostics, version)) = recipe_diagnostics(&uri, root.as_deref(), state) else {
+            continue;
+        };
+        let previous = previous
+            .iter()
+            .find(|p| p.uri == uri)
+            .map(|p| p.value.as_str());
+        items.push(match report(diagnostics, previous) {
+            DocumentDiagnosticReportKind::Full(full_document_diagnostic_report) => {
+                WorkspaceDocumentDiagnosticReport::Full(WorkspaceFullDocumentDiagnosticReport {
+                    uri,
+                    version,
+                    full_document_diagnostic_report,
+                })
+            }
+            DocumentDiagnosticReportKind::Unchanged(unchanged_document_diagnostic_report) => {
+                WorkspaceDocumentDiagnosticReport::Unchanged(
+                    WorkspaceUnchangedDocumentDiagnosticReport {
+                        uri,
+                        version,
+                        unchanged_document_diagnostic_report,
+                    },
+                )
+            }
+        });
+        tokio::task::yield_now().await;
+    }
+
+    WorkspaceDiagnosticReport { items }
+}
+
+/// Report a lint's diagnostics with the severity its level sets, if any
+fn with_level(
+    diagnostics: impl IntoIterator<Item = Diagnostic>,
+    level: LintLevel,
+) -> impl Iterator<Item = Diagnostic> {
+    diagnostics.into_iter().map(move |mut diag| {
+        if let Some(severity) = level.severity() {
+            diag.severity = Some(severity);
+        }
+        diag
+    })
+}
+
+/// A parser diagnostic. Its range is the first label's; every label is kept
+/// as related information and the hints are added to the message. (The
+/// parser's diagnostics have no code.)
+fn convert_source_diag(diag: &SourceDiag, doc: &Document) -> Option<Diagnostic> {
+    let range_of = |span: &cooklang::Span| span_to_range(span.start(), span.end(), &doc.line_index);
     let range = diag
         .labels
         .first()
-        .map(|(span, _)| span_to_range(span.start(), span.end(), line_index))
+        .map(|(span, _)| range_of(span))
         .unwrap_or_default();
 
     let severity = match diag.severity {
@@ -50,11 +227,165 @@ fn convert_source_diag(
         Severity::Warning => DiagnosticSeverity::WARNING,
     };
 
+    let related_information: Vec<_> = diag
+        .labels
+        .iter()
+        .map(|(span, label)| DiagnosticRelatedInformation {
+            location: Location::new(doc.uri.clone(), range_of(span)),
+            message: label.as_deref().unwrap_or(&diag.message).to_string(),
+        })
+        .collect();
+
+    let mut message = diag.message.to_string();
+    for hint in &diag.hints {
+        message.push_str("\nHint: ");
+        message.push_str(hint);
+    }
+
     Some(Diagnostic {
         range,
         severity: Some(severity),
         source: Some("cooklang".into()),
-        message: diag.message.to_string(),
+        message,
+        related_information: (!related_information.is_empty()).then_some(related_information),
         ..Default::default()
     })
 }
+
+/// Report recipe references (`@./sauces/Pesto{}`) whose file doesn't exist,
+/// e.g. a menu listing a recipe that was renamed or deleted.
+fn reference_diagnostics(
+    doc: &Document,
+    workspace_root: Option<&Path>,
+    strings: &Strings,
+) -> Vec<Diagnostic> {
+    let doc_dir = references::document_dir(&doc.uri);
+
+    references::located_references(doc)
+        .into_iter()
+        .filter_map(|(span, ingredient)| {
+            let reference = ingredient.reference.as_ref()?;
+            if references::resolve(reference, doc_dir.as_deref(), workspace_root).is_some() {
+                return None;
+            }
+            Some(Diagnostic {
+                range: span_to_range(span.start(), span.end(), &doc.line_index),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("cooklang".into()),
+                message: fill(strings.reference_not_found, &[&reference.path("/")]),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::{NumberOrString, Url};
+
+    fn diagnostics(content: &str) -> Vec<Diagnostic> {
+        let state = ServerState::new();
+        let uri = Url::parse("file:///test.cook").unwrap();
+        // With metadata, so `missing-metadata` doesn't report anything
+        let content = format!("---\ntitle: Test\nservings: 1\n---\n{content}");
+        state.open_document(uri.clone(), 1, content);
+        let doc = state.get_document(&uri).unwrap();
+        get_diagnostics(&doc, None, &state)
+    }
+
+    #[test]
+    fn parser_labels_and_hints_are_kept() {
+        let diagnostics = diagnostics("Add @salt{1%}.");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "Empty quantity unit\nHint: Add a unit or remove the separator"
+        );
+        let related = diagnostics[0].related_information.as_ref().unwrap();
+        assert_eq!(related[0].message, "remove this");
+        assert_eq!(related[0].location.range, diagnostics[0].range);
+    }
+
+    #[test]
+    fn metadata_checks_supersede_parser_warnings() {
+        let state = ServerState::new();
+        let uri = Url::parse("file:///test.cook").unwrap();
+        let content = "---\ntitle: Test\nservings: four\n---\nMix @a{1}.";
+        state.open_document(uri.clone(), 1, content.into());
+        let doc = state.get_document(&uri).unwrap();
+        let messages: Vec<_> = get_diagnostics(&doc, None, &state)
+            .into_iter()
+            .map(|diag| (diag.range.start.line, diag.message))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![(
+                2,
+                "'servings' should be a whole number of servings, e.g. `servings: 4`".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn quick_fixes_supersede_parser_diagnostics() {
+        // The parser sees a timer without duration followed by text
+        let diagnostics = diagnostics("Rest ~dough{10%min.");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("unclosed-quantity".into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn workspace_report_skips_unchanged_recipes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let metadata = "---\ntitle: Test\nservings: 1\n---\n";
+        let pasta = dir.path().join("pasta.cook");
+        std::fs::write(&pasta, format!("{metadata}Serve with @./sauces/Pesto{{}}.")).unwrap();
+        std::fs::write(
+            dir.path().join("soup.cook"),
+            format!("{metadata}Boil @water{{1%l}}."),
+        )
+        .unwrap();
+        let state = ServerState::new();
+        state.index_workspace(dir.path());
+
+        let first = workspace_report(&state, Some(dir.path()), &[]).await;
+        let mut previous = Vec::new();
+        let mut problems = Vec::new();
+        for item in &first.items {
+            let WorkspaceDocumentDiagnosticReport::Full(report) = item else {
+                panic!("first report is unchanged");
+            };
+            let report_id = report.full_document_diagnostic_report.result_id.clone();
+            previous.push(PreviousResultId {
+                uri: report.uri.clone(),
+                value: report_id.unwrap(),
+            });
+            for diag in &report.full_document_diagnostic_report.items {
+                problems.push(diag.message.clone());
+            }
+        }
+        assert_eq!(previous.len(), 2);
+        assert_eq!(
+            problems,
+            vec!["Referenced recipe not found: ./sauces/Pesto"]
+        );
+
+        // Only the recipe that changed (here, in the editor) is sent again
+        let uri = Url::from_file_path(&pasta).unwrap();
+        state.open_document(uri.clone(), 3, format!("{metadata}Serve with @basil{{}}."));
+        let second = workspace_report(&state, Some(dir.path()), &previous).await;
+        let kinds: Vec<_> = second
+            .items
+            .iter()
+            .map(|item| match item {
+                WorkspaceDocumentDiagnosticReport::Full(r) => ("full", r.version),
+                WorkspaceDocumentDiagnosticReport::Unchanged(r) => ("unchanged", r.version),
+            })
+            .collect();
+        assert_eq!(kinds, vec![("full", Some(3)), ("unchanged", None)]);
+    }
+}
@@ -4,6 +4,22 @@ use tower_lsp::lsp_types::Url;
 
 use crate::utils::line_index::LineIndex;
 
+/// The parser used for every recipe the server reads, open or not.
+pub fn parser() -> CooklangParser {
+    parser_with(Extensions::all())
+}
+
+/// The parser for open documents, with the extensions the settings enable.
+pub fn parser_with(extensions: Extensions) -> CooklangParser {
+    CooklangParser::new(extensions, Default::default())
+}
+
+/// Parse a recipe that is not open in the editor (e.g. a referenced recipe),
+/// keeping whatever output the parser could recover.
+pub fn parse_recipe(content: &str) -> Option<Recipe> {
+    parser().parse(content).into_output()
+}
+
 /// Represents a parsed Cooklang document
 #[derive(Debug)]
 pub struct Document {
@@ -16,6 +32,8 @@ pub struct Document {
     pub parse_errors: Vec<SourceDiag>,
     /// Warnings from parsing
     pub parse_warnings: Vec<SourceDiag>,
+    /// Parser extensions the document is parsed with
+    pub extensions: Extensions,
 }
 
 #[derive(Debug, Clone)]
@@ -25,6 +43,15 @@ pub struct ParseResult {
 
 impl Document {
     pub fn new(uri: Url, version: i32, content: String) -> Self {
+        Self::with_extensions(uri, version, content, Extensions::all())
+    }
+
+    pub fn with_extensions(
+        uri: Url,
+        version: i32,
+        content: String,
+        extensions: Extensions,
+    ) -> Self {
         let line_index = LineIndex::new(&content);
         let mut doc = Self {
             uri,
@@ -34,6 +61,7 @@ impl Document {
             parse_result: None,
             parse_errors: Vec::new(),
             parse_warnings: Vec::new(),
+            extensions,
         };
         doc.reparse();
         doc
@@ -46,9 +74,16 @@ impl Document {
         self.reparse();
     }
 
+    /// Parse again with other extensions
+    pub fn set_extensions(&mut self, extensions: Extensions) {
+        if extensions != self.extensions {
+            self.extensions = extensions;
+            self.reparse();
+        }
+    }
+
     fn reparse(&mut self) {
-        let parser = CooklangParser::new(Extensions::all(), Default::default());
-        let result = parser.parse(&self.content);
+        let result = parser_with(self.extensions).parse(&self.content);
 
         // Get errors and warnings from the report
         let report = result.report();
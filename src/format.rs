@@ -0,0 +1,385 @@
+//! Recipe formatting, shared by `textDocument/formatting` and
+//! `cooklang-lsp format` so both write recipes the same way.
+//!
+//! The formatter changes the layout of a recipe, never what it says:
+//!
+//! - trailing whitespace is removed, blank lines between blocks collapse to
+//!   one and the file ends with a single newline (the YAML frontmatter is
+//!   left as it is)
+//! - section headers are written in the configured style
+//! - with compact quantities, the spaces around `%` and inside the braces of
+//!   quantities are dropped (`{ 200 % g }` -> `{200%g}`)
+//!
+//! Line endings are kept. A recipe with parse errors is left as it is.
+
+use std::fmt::Write as _;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use cooklang::parser::{Event, PullParser};
+use cooklang::Extensions;
+use tower_lsp::lsp_types::TextEdit;
+
+use crate::document::Document;
+use crate::index;
+use crate::lsp::LineEndings;
+use crate::project_config;
+use crate::settings::{FormatSettings, SectionStyle};
+use crate::utils::position::span_to_range;
+
+/// `content` formatted with `settings`
+pub fn format(content: &str, extensions: Extensions, settings: &FormatSettings) -> String {
+    let (normalized, endings) = LineEndings::normalize(content.to_string());
+    let Some(replacements) = replacements(&normalized, extensions, settings) else {
+        return content.to_string();
+    };
+
+    let mut text = normalized;
+    for (range, replacement) in replacements.into_iter().rev() {
+        text.replace_range(range, &replacement);
+    }
+    endings.apply(&normalize_lines(&text))
+}
+
+/// The edits formatting `doc`: one edit replacing what changed, or none
+pub fn edits(doc: &Document, settings: &FormatSettings) -> Vec<TextEdit> {
+    let content = doc.content.as_str();
+    let formatted = format(content, doc.extensions, settings);
+    if formatted == content {
+        return Vec::new();
+    }
+
+    // Keep the unchanged start and end, on character boundaries
+    let prefix = content
+        .char_indices()
+        .zip(formatted.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(content.len().min(formatted.len()), |((i, _), _)| i);
+    let suffix = content[prefix..]
+        .chars()
+        .rev()
+        .zip(formatted[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum::<usize>();
+
+    vec![TextEdit {
+        range: span_to_range(prefix, content.len() - suffix, &doc.line_index),
+        new_text: formatted[prefix..formatted.len() - suffix].to_string(),
+    }]
+}
+
+/// Rewrites of section headers and quantities, in source order. `None` if
+/// the recipe has errors.
+fn replacements(
+    content: &str,
+    extensions: Extensions,
+    settings: &FormatSettings,
+) -> Option<Vec<(Range<usize>, String)>> {
+    let mut replacements = Vec::new();
+    for event in PullParser::new(content, extensions) {
+        let quantity = match event {
+            Event::Error(_) => return None,
+            Event::Section { name: Some(name) } => {
+                let start = content[..name.span().start()]
+                    .rfind('\n')
+                    .map_or(0, |i| i + 1);
+                let end = content[name.span().end()..]
+                    .find('\n')
+                    .map_or(content.len(), |i| name.span().end() + i);
+                let line = &content[start..end];
+                let header = match settings.section_style {
+                    SectionStyle::Keep => continue,
+                    SectionStyle::Single => format!("= {}", name.text().trim()),
+                    SectionStyle::Double => format!("== {} ==", name.text().trim()),
+                };
+                // Headers with comments are left alone
+                if !line.contains("--") && !line.contains("[-") && line != header {
+                    replacements.push((start..end, header));
+                }
+                continue;
+            }
+            Event::Ingredient(ingredient) => ingredient.quantity.as_ref().map(|q| q.span()),
+            Event::Cookware(cookware) => cookware.quantity.as_ref().map(|q| q.span()),
+            Event::Timer(timer) => timer.quantity.as_ref().map(|q| q.span()),
+            _ => None,
+        };
+
+        let Some(span) = quantity.filter(|_| settings.compact_quantities) else {
+            continue;
+        };
+        let text = &content[span.range()];
+        let compact = match text.split_once('%') {
+            Some((value, unit)) if !unit.contains('%') => {
+                format!("{}%{}", value.trim(), unit.trim())
+            }
+            _ => text.trim().to_string(),
+        };
+        if compact != text {
+            replacements.push((span.range(), compact));
+        }
+    }
+    Some(replacements)
+}
+
+/// Trailing whitespace and extra blank lines removed, outside the
+/// frontmatter, and a single newline at the end
+fn normalize_lines(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut out = String::new();
+
+    let mut body = 0;
+    if lines.first() == Some(&"---") {
+        if let Some(end) = lines[1..].iter().position(|line| *line == "---") {
+            body = end + 2;
+            for line in &lines[..body] {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+    let body_start = out.len();
+
+    let mut blank = false;
+    for line in &lines[body..] {
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank = true;
+            continue;
+        }
+        if blank && out.len() > body_start {
+            out.push('\n');
+        }
+        blank = false;
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// A recipe file that formatting changes
+#[derive(Debug)]
+pub struct FormattedFile {
+    pub path: PathBuf,
+    pub original: String,
+    pub formatted: String,
+}
+
+/// Format the recipes at `paths` (files, or directories to search for
+/// `.cook` and `.menu` files) with the settings of the workspace `root`.
+/// Only the files that change are returned; nothing is written.
+pub fn format_files(root: &Path, paths: &[PathBuf]) -> io::Result<Vec<FormattedFile>> {
+    let (settings, _) = project_config::workspace_settings(root);
+    let extensions = settings.extensions();
+    let mut changed = Vec::new();
+    for path in index::recipe_paths(paths)? {
+        let original = std::fs::read_to_string(&path)?;
+        let formatted = format(&original, extensions, &settings.format);
+        if formatted != original {
+            changed.push(FormattedFile {
+                path,
+                original,
+                formatted,
+            });
+        }
+    }
+    Ok(changed)
+}
+
+/// What formatting changes in `file`, as a unified diff
+pub fn diff(file: &FormattedFile) -> String {
+    const CONTEXT: usize = 3;
+
+    let old: Vec<&str> = file.original.split_inclusive('\n').collect();
+    let new: Vec<&str> = file.formatted.split_inclusive('\n').collect();
+    let ops = line_ops(&old, &new);
+
+    let path = file.path.display();
+    let mut out = format!("--- {path}\n+++ {path}\n");
+    let changes: Vec<usize> = (0..ops.len())
+        .filter(|&i| !matches!(ops[i].0, Op::Equal))
+        .collect();
+    let mut i = 0;
+    while i < changes.len() {
+        // Changes closer than twice the context share a hunk
+        let mut last = i;
+        while last + 1 < changes.len() && changes[last + 1] - changes[last] <= 2 * CONTEXT {
+            last += 1;
+        }
+        let start = changes[i].saturating_sub(CONTEXT);
+        let end = (changes[last] + CONTEXT + 1).min(ops.len());
+        let hunk = &ops[start..end];
+
+        let old_len = hunk.iter().filter(|op| !matches!(op.0, Op::Insert)).count();
+        let new_len = hunk.iter().filter(|op| !matches!(op.0, Op::Delete)).count();
+        let (old_start, new_start) = (hunk[0].1, hunk[0].2);
+        let _ = writeln!(
+            out,
+            "@@ -{},{old_len} +{},{new_len} @@",
+            old_start + usize::from(old_len > 0),
+            new_start + usize::from(new_len > 0),
+        );
+        for &(op, o, n) in hunk {
+            let (sign, line) = match op {
+                Op::Equal => (' ', old[o]),
+                Op::Delete => ('-', old[o]),
+                Op::Insert => ('+', new[n]),
+            };
+            out.push(sign);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        i = last + 1;
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// A shortest edit turning `old` into `new`, from their longest common
+/// subsequence, with the position in both at each step
+fn line_ops(old: &[&str], new: &[&str]) -> Vec<(Op, usize, usize)> {
+    // lcs[i][j]: length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push((Op::Equal, i, j));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push((Op::Delete, i, j));
+            i += 1;
+        } else {
+            ops.push((Op::Insert, i, j));
+            j += 1;
+        }
+    }
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::{Position, Range as LspRange, Url};
+
+    fn format_with(content: &str, section_style: SectionStyle, compact: bool) -> String {
+        let settings = FormatSettings {
+            section_style,
+            compact_quantities: compact,
+        };
+        format(content, Extensions::all(), &settings)
+    }
+
+    #[test]
+    fn layout() {
+        let content = "---\ntitle: Soup  \n\nservings: 2\n---\n\n\nBoil @water{ 1 % l }.   \n\n\n\nAdd #pot{ 2 } and wait ~{10 %min}.";
+        assert_eq!(
+            format_with(content, SectionStyle::Keep, true),
+            "---\ntitle: Soup  \n\nservings: 2\n---\nBoil @water{1%l}.\n\nAdd #pot{2} and wait ~{10%min}.\n"
+        );
+        assert_eq!(
+            format_with(content, SectionStyle::Keep, false),
+            "---\ntitle: Soup  \n\nservings: 2\n---\nBoil @water{ 1 % l }.\n\nAdd #pot{ 2 } and wait ~{10 %min}.\n"
+        );
+        assert_eq!(format_with("", SectionStyle::Keep, true), "");
+        // Line endings are kept
+        assert_eq!(
+            format_with(
+                "Add @salt{1 %tsp}.\r\n\r\n\r\nStir.",
+                SectionStyle::Keep,
+                true
+            ),
+            "Add @salt{1%tsp}.\r\n\r\nStir.\r\n"
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let content = "== Dough ==\nMix.\n\n=Sauce\nStir.\n\n= Filling -- for later\nFill.\n";
+        assert_eq!(
+            format_with(content, SectionStyle::Single, true),
+            "= Dough\nMix.\n\n= Sauce\nStir.\n\n= Filling -- for later\nFill.\n"
+        );
+        assert_eq!(
+            format_with(content, SectionStyle::Double, true),
+            "== Dough ==\nMix.\n\n== Sauce ==\nStir.\n\n= Filling -- for later\nFill.\n"
+        );
+    }
+
+    #[test]
+    fn recipes_with_errors_are_left_alone() {
+        let content = "Add @salt{1 % tsp}   \nRest ~rest{10%min\n\n\n";
+        assert_eq!(format_with(content, SectionStyle::Double, true), content);
+    }
+
+    #[test]
+    fn edits_replace_what_changed() {
+        let doc = Document::new(
+            Url::parse("file:///soup.cook").unwrap(),
+            1,
+            "Boil @water{1 % l}.\nAdd @salt.".into(),
+        );
+        let edits = edits(&doc, &FormatSettings::default());
+        assert_eq!(
+            edits,
+            vec![TextEdit {
+                range: LspRange::new(Position::new(0, 13), Position::new(1, 10)),
+                new_text: "%l}.\nAdd @salt.\n".into(),
+            }]
+        );
+        let formatted = Document::new(
+            doc.uri.clone(),
+            2,
+            format(&doc.content, doc.extensions, &FormatSettings::default()),
+        );
+        assert!(super::edits(&formatted, &FormatSettings::default()).is_empty());
+    }
+
+    #[test]
+    fn files_and_diff() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join(project_config::FILE_NAME),
+            "[format]\nsection-style = \"double\"\n",
+        )
+        .unwrap();
+        let soup = dir.path().join("soup.cook");
+        std::fs::write(&soup, "= Broth\nBoil @water{1%l}.\n\n\nAdd @salt.\n").unwrap();
+        std::fs::write(dir.path().join("tea.cook"), "Steep @tea{1%bag}.\n").unwrap();
+
+        let changed = format_files(dir.path(), &[dir.path().to_path_buf()]).unwrap();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].path, soup);
+        assert_eq!(
+            changed[0].formatted,
+            "== Broth ==\nBoil @water{1%l}.\n\nAdd @salt.\n"
+        );
+        let path = soup.display();
+        assert_eq!(
+            diff(&changed[0]),
+            format!(
+                "--- {path}\n+++ {path}\n@@ -1,5 +1,4 @@\n-= Broth\n+== Broth ==\n Boil @water{{1%l}}.\n \n-\n Add @salt.\n"
+            )
+        );
+    }
+}
@@ -1,10 +1,21 @@
+use std::path::Path;
+
+use cooklang::Recipe;
 use tower_lsp::lsp_types::{Hover, HoverContents, HoverParams, MarkupContent, MarkupKind};
 
-use crate::document::Document;
+use crate::document::{parser, Document};
+use crate::i18n::Strings;
+use crate::references;
+use crate::state::ServerState;
 use crate::utils::components::{component_at, scan_components, ComponentKind};
 use crate::utils::position::position_to_offset;
 
-pub fn get_hover(doc: &Document, params: &HoverParams) -> Option<Hover> {
+pub fn get_hover(
+    doc: &Document,
+    params: &HoverParams,
+    state: &ServerState,
+    workspace_root: Option<&Path>,
+) -> Option<Hover> {
     let offset = position_to_offset(
         params.text_document_position_params.position,
         &doc.line_index,
@@ -16,26 +27,31 @@ pub fn get_hover(doc: &Document, params: &HoverParams) -> Option<Hover> {
     // Find what element is at the cursor position
     // Look backwards and forwards to find the element boundaries
     let (element_type, element_text) = find_element_at_offset(content, offset)?;
+    let strings = state.language_for(doc).strings();
 
     let hover_text = match element_type {
         ElementType::Ingredient => {
+            // A reference to another recipe or menu: describe the target
+            if let Some(text) = reference_hover(doc, offset, state, workspace_root, strings) {
+                return Some(create_hover(text));
+            }
             // Find the ingredient in the parsed recipe
             let name = extract_name(&element_text);
             for ingredient in &parse_result.recipe.ingredients {
                 if ingredient.name.eq_ignore_ascii_case(&name) {
-                    return Some(create_hover(format_ingredient_hover(ingredient)));
+                    return Some(create_hover(format_ingredient_hover(ingredient, strings)));
                 }
             }
-            format!("**Ingredient:** {}", name)
+            format!("**{}:** {}", strings.ingredient, name)
         }
         ElementType::Cookware => {
             let name = extract_name(&element_text);
             for cookware in &parse_result.recipe.cookware {
                 if cookware.name.eq_ignore_ascii_case(&name) {
-                    return Some(create_hover(format_cookware_hover(cookware)));
+                    return Some(create_hover(format_cookware_hover(cookware, strings)));
                 }
             }
-            format!("**Cookware:** {}", name)
+            format!("**{}:** {}", strings.cookware, name)
         }
         ElementType::Timer => {
             // Find matching timer by name or duration
@@ -43,24 +59,34 @@ pub fn get_hover(doc: &Document, params: &HoverParams) -> Option<Hover> {
             for timer in &parse_result.recipe.timers {
                 let timer_name = timer.name.as_deref().unwrap_or("");
                 if timer_name.eq_ignore_ascii_case(&name) || name.is_empty() {
-                    return Some(create_hover(format_timer_hover(timer)));
+                    return Some(create_hover(format_timer_hover(timer, strings)));
                 }
             }
             format!(
-                "**Timer:** {}",
-                if name.is_empty() { "unnamed" } else { &name }
+                "**{}:** {}",
+                strings.timer,
+                if name.is_empty() {
+                    strings.unnamed
+                } else {
+                    &name
+                }
             )
         }
         ElementType::Section => {
-            format!("**Section:** {}", element_text.trim_matches('=').trim())
+            format!(
+                "**{}:** {}",
+                strings.section,
+                element_text.trim_matches('=').trim()
+            )
         }
         ElementType::Metadata => {
             format!(
-                "**Metadata:** {}",
+                "**{}:** {}",
+                strings.metadata,
                 element_text.trim_start_matches('>').trim()
             )
         }
-        ElementType::Comment => "**Comment**".to_string(),
+        ElementType::Comment => format!("**{}**", strings.comment),
     };
 
     Some(create_hover(hover_text))
@@ -129,49 +155,137 @@ fn create_hover(text: String) -> Hover {
     }
 }
 
-fn format_ingredient_hover(ingredient: &cooklang::model::Ingredient) -> String {
+/// Hover text for a recipe reference under the cursor, if there is one.
+fn reference_hover(
+    doc: &Document,
+    offset: usize,
+    state: &ServerState,
+    workspace_root: Option<&Path>,
+    strings: &Strings,
+) -> Option<String> {
+    let (_, ingredient) = references::located_references(doc)
+        .into_iter()
+        .find(|(span, _)| span.start() <= offset && offset < span.end())?;
+    let reference = ingredient.reference.as_ref()?;
+    let path = reference.path("/");
+
+    let doc_dir = references::document_dir(&doc.uri);
+    let Some(file) = references::resolve(reference, doc_dir.as_deref(), workspace_root) else {
+        return Some(format!(
+            "**{}:** {}\n\n*{}*",
+            strings.recipe, path, strings.recipe_not_found
+        ));
+    };
+    let mut recipe = references::load_recipe(&file, state)?;
+    // Preview the amounts the reference asks for, not the recipe as written.
+    references::scale_for_reference(&mut recipe, ingredient);
+
+    Some(format_reference_hover(&path, &recipe, strings))
+}
+
+fn format_reference_hover(path: &str, recipe: &Recipe, strings: &Strings) -> String {
+    let mut parts = Vec::new();
+    let metadata = &recipe.metadata;
+
+    let parser = parser();
+
+    match metadata.title() {
+        Some(title) => parts.push(format!("**{}:** {} (`{}`)", strings.recipe, title, path)),
+        None => parts.push(format!("**{}:** {}", strings.recipe, path)),
+    }
+
+    if let Some(description) = metadata.description() {
+        parts.push(description.trim().to_string());
+    }
+
+    if let Some(servings) = metadata.servings() {
+        parts.push(format!("**{}:** {}", strings.servings, servings));
+    }
+
+    if let Some(time) = metadata.time(parser.converter()) {
+        parts.push(format!(
+            "**{}:** {}",
+            strings.total_time,
+            format_minutes(time.total())
+        ));
+    }
+
+    let ingredients: Vec<String> = recipe
+        .group_ingredients(parser.converter())
+        .into_iter()
+        .filter(|entry| entry.ingredient.modifiers().should_be_listed())
+        .map(|entry| {
+            let name = entry.ingredient.display_name();
+            if entry.quantity.is_empty() {
+                format!("- {}", name)
+            } else {
+                format!("- {}: {}", name, entry.quantity)
+            }
+        })
+        .collect();
+    if !ingredients.is_empty() {
+        parts.push(format!(
+            "**{}:**\n{}",
+            strings.ingredients,
+            ingredients.join("\n")
+        ));
+    }
+
+    parts.join("\n\n")
+}
+
+/// Format a duration in minutes, e.g. "45 min" or "1 h 30 min".
+fn format_minutes(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{} min", m),
+        (h, 0) => format!("{} h", h),
+        (h, m) => format!("{} h {} min", h, m),
+    }
+}
+
+fn format_ingredient_hover(ingredient: &cooklang::model::Ingredient, strings: &Strings) -> String {
     let mut parts = Vec::new();
 
-    parts.push(format!("**Ingredient:** {}", ingredient.name));
+    parts.push(format!("**{}:** {}", strings.ingredient, ingredient.name));
 
     if let Some(ref quantity) = ingredient.quantity {
-        parts.push(format!("**Quantity:** {}", quantity));
+        parts.push(format!("**{}:** {}", strings.quantity, quantity));
     }
 
     if let Some(ref note) = ingredient.note {
-        parts.push(format!("**Note:** {}", note));
+        parts.push(format!("**{}:** {}", strings.note, note));
     }
 
     parts.join("\n\n")
 }
 
-fn format_cookware_hover(cookware: &cooklang::model::Cookware) -> String {
+fn format_cookware_hover(cookware: &cooklang::model::Cookware, strings: &Strings) -> String {
     let mut parts = Vec::new();
 
-    parts.push(format!("**Cookware:** {}", cookware.name));
+    parts.push(format!("**{}:** {}", strings.cookware, cookware.name));
 
     if let Some(ref quantity) = cookware.quantity {
-        parts.push(format!("**Quantity:** {}", quantity));
+        parts.push(format!("**{}:** {}", strings.quantity, quantity));
     }
 
     if let Some(ref note) = cookware.note {
-        parts.push(format!("**Note:** {}", note));
+        parts.push(format!("**{}:** {}", strings.note, note));
     }
 
     parts.join("\n\n")
 }
 
-fn format_timer_hover(timer: &cooklang::model::Timer) -> String {
+fn format_timer_hover(timer: &cooklang::model::Timer, strings: &Strings) -> String {
     let mut parts = Vec::new();
 
     if let Some(ref name) = timer.name {
-        parts.push(format!("**Timer:** {}", name));
+        parts.push(format!("**{}:** {}", strings.timer, name));
     } else {
-        parts.push("**Timer**".to_string());
+        parts.push(format!("**{}**", strings.timer));
     }
 
     if let Some(ref quantity) = timer.quantity {
-        parts.push(format!("**Duration:** {}", quantity));
+        parts.push(format!("**{}:** {}", strings.duration, quantity));
     }
 
     parts.join("\n\n")
@@ -200,12 +314,23 @@ mod tests {
             },
             work_done_progress_params: Default::default(),
         };
-        match get_hover(&doc, &params).unwrap().contents {
+        match get_hover(&doc, &params, &ServerState::new(), None)
+            .unwrap()
+            .contents
+        {
             HoverContents::Markup(m) => m.value,
             _ => panic!("expected markup hover"),
         }
     }
 
+    #[test]
+    fn hover_uses_recipe_locale() {
+        let content = ">> locale: de\nDen @Mehl{200%g} sieben.";
+        let hover = hover_at(content, content.find("Mehl").unwrap());
+        assert!(hover.contains("**Zutat:** Mehl"), "got: {hover}");
+        assert!(hover.contains("**Menge:** 200 g"), "got: {hover}");
+    }
+
     #[test]
     fn hover_multi_word_ingredient_shows_full_name_and_quantity() {
         // Regression for cooklang/CookVSCode#10: hovering anywhere on a
@@ -230,4 +355,63 @@ mod tests {
             "got: {hover}"
         );
     }
+
+    #[test]
+    fn hover_recipe_reference_shows_scaled_preview() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(
+            root.join("Lasagna.cook"),
+            "---\ntitle: Classic Lasagna\ndescription: Layers of pasta and ragù.\nservings: 4\n\
+             prep time: 30 min\ncook time: 1h\n---\n\nBake @pasta{500%g} with @ragù{1%l} and @-salt.",
+        )
+        .unwrap();
+
+        let content = "== Monday ==\nDinner: @./Lasagna{8%servings}\nLunch: @./Missing{}\n";
+        let doc = Document::new(
+            Url::from_file_path(root.join("Week.menu")).unwrap(),
+            1,
+            content.to_string(),
+        );
+        let hover_at_text = |needle: &str| {
+            let (line, character) = doc
+                .line_index
+                .line_col(content.find(needle).unwrap() as u32);
+            let params = HoverParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: doc.uri.clone(),
+                    },
+                    position: Position { line, character },
+                },
+                work_done_progress_params: Default::default(),
+            };
+            match get_hover(&doc, &params, &ServerState::new(), Some(root))
+                .unwrap()
+                .contents
+            {
+                HoverContents::Markup(m) => m.value,
+                _ => panic!("expected markup hover"),
+            }
+        };
+
+        let hover = hover_at_text("Lasagna");
+        assert!(
+            hover.contains("**Recipe:** Classic Lasagna (`./Lasagna`)"),
+            "got: {hover}"
+        );
+        assert!(hover.contains("Layers of pasta and ragù."), "got: {hover}");
+        // Scaled to the 8 servings the reference asks for.
+        assert!(hover.contains("**Servings:** 8"), "got: {hover}");
+        assert!(hover.contains("**Total time:** 1 h 30 min"), "got: {hover}");
+        assert!(
+            hover.contains("**Ingredients:**\n- pasta: 1000 g\n- ragù: 2 l"),
+            "got: {hover}"
+        );
+        // Hidden ingredients are not listed.
+        assert!(!hover.contains("salt"), "got: {hover}");
+
+        let hover = hover_at_text("Missing");
+        assert!(hover.contains("*Recipe not found*"), "got: {hover}");
+    }
 }
@@ -0,0 +1,297 @@
+//! Languages for completion data, hover text and the server's own
+//! diagnostics.
+//!
+//! A recipe's language comes from its `locale` metadata. Recipes without one
+//! use the server's default language, taken from the `locale`
+//! initialization option or the client's locale.
+
+use crate::document::Document;
+
+/// A language the server has completion data and strings for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+}
+
+impl Language {
+    /// The language of a locale such as `de`, `de_AT` or `fr-CA`
+    pub fn from_locale(locale: &str) -> Option<Self> {
+        let code = locale
+            .split(['_', '-'])
+            .next()
+            .unwrap_or(locale)
+            .to_lowercase();
+        match code.as_str() {
+            "en" => Some(Self::English),
+            "de" => Some(Self::German),
+            "fr" => Some(Self::French),
+            "es" => Some(Self::Spanish),
+            _ => None,
+        }
+    }
+
+    /// The language set by the document's `locale` metadata, if it is one
+    /// the server knows
+    pub fn of_document(doc: &Document) -> Option<Self> {
+        let result = doc.parse_result.as_ref()?;
+        let (language, _) = result.recipe.metadata.locale()?;
+        Self::from_locale(language)
+    }
+
+    pub fn strings(self) -> &'static Strings {
+        match self {
+            Self::English => &EN,
+            Self::German => &DE,
+            Self::French => &FR,
+            Self::Spanish => &ES,
+        }
+    }
+}
+
+/// Hover labels and diagnostic messages. In messages, each `{}` is replaced
+/// by an argument (see [`fill`]).
+#[derive(Debug)]
+pub struct Strings {
+    pub ingredient: &'static str,
+    pub cookware: &'static str,
+    pub timer: &'static str,
+    pub unnamed: &'static str,
+    pub section: &'static str,
+    pub metadata: &'static str,
+    pub comment: &'static str,
+    pub recipe: &'static str,
+    pub recipe_not_found: &'static str,
+    pub servings: &'static str,
+    pub total_time: &'static str,
+    pub ingredients: &'static str,
+    pub quantity: &'static str,
+    pub note: &'static str,
+    pub duration: &'static str,
+    /// Key, key
+    pub servings_not_a_number: &'static str,
+    /// Value, key
+    pub invalid_duration: &'static str,
+    /// Value
+    pub invalid_locale: &'static str,
+    /// Path
+    pub reference_not_found: &'static str,
+    /// Syntax, extension name
+    pub extension_disabled: &'static str,
+    pub unclosed_quantity: &'static str,
+    pub unclosed_note: &'static str,
+    /// Name as read, `{}`
+    pub unbraced_name: &'static str,
+    /// Quantity
+    pub quantity_separator: &'static str,
+    /// Quantity
+    pub decimal_comma: &'static str,
+    /// Replacement (quick fix title)
+    pub change_to: &'static str,
+    /// Ingredient
+    pub missing_quantity: &'static str,
+    /// Cookware
+    pub unused_cookware: &'static str,
+    /// Ingredient, line, ingredient
+    pub duplicate_ingredient: &'static str,
+    pub first_defined_here: &'static str,
+    /// Sentences, maximum
+    pub long_step: &'static str,
+    /// Key
+    pub missing_metadata: &'static str,
+    pub failed_to_parse: &'static str,
+}
+
+pub static EN: Strings = Strings {
+    ingredient: "Ingredient",
+    cookware: "Cookware",
+    timer: "Timer",
+    unnamed: "unnamed",
+    section: "Section",
+    metadata: "Metadata",
+    comment: "Comment",
+    recipe: "Recipe",
+    recipe_not_found: "Recipe not found",
+    servings: "Servings",
+    total_time: "Total time",
+    ingredients: "Ingredients",
+    quantity: "Quantity",
+    note: "Note",
+    duration: "Duration",
+    servings_not_a_number: "'{}' should be a whole number of servings, e.g. `{}: 4`",
+    invalid_duration: "Could not read '{}' as a duration for '{}': use minutes or units, e.g. `45 min` or `1h 30m`",
+    invalid_locale: "Invalid locale '{}': expected a language code such as `en` or `en_GB`",
+    reference_not_found: "Referenced recipe not found: {}",
+    extension_disabled: "`{}` needs the `{}` parser extension, which is disabled",
+    unclosed_quantity: "Missing `}`: the quantity is read as plain text",
+    unclosed_note: "Missing `)`: the note is read as plain text",
+    unbraced_name: "Only `{}` is read as the name: a name with spaces ends with `{}`",
+    quantity_separator: "`{}` is read as text: separate the amount and the unit with `%`",
+    decimal_comma: "`{}` is read as text: use a decimal point",
+    change_to: "Change to `{}`",
+    missing_quantity: "No quantity for `{}`",
+    unused_cookware: "`{}` is declared but never used in a step",
+    duplicate_ingredient: "`{}` is already defined on line {}: write `@&{}` to refer to it",
+    first_defined_here: "First defined here",
+    long_step: "Step has {} sentences, more than {}: consider splitting it",
+    missing_metadata: "No `{}` metadata",
+    failed_to_parse: "Failed to parse recipe",
+};
+
+pub static DE: Strings = Strings {
+    ingredient: "Zutat",
+    cookware: "Kochgeschirr",
+    timer: "Timer",
+    unnamed: "ohne Namen",
+    section: "Abschnitt",
+    metadata: "Metadaten",
+    comment: "Kommentar",
+    recipe: "Rezept",
+    recipe_not_found: "Rezept nicht gefunden",
+    servings: "Portionen",
+    total_time: "Gesamtzeit",
+    ingredients: "Zutaten",
+    quantity: "Menge",
+    note: "Notiz",
+    duration: "Dauer",
+    servings_not_a_number: "'{}' sollte eine ganze Zahl von Portionen sein, z. B. `{}: 4`",
+    invalid_duration: "'{}' ist keine gültige Dauer für '{}': Minuten oder Einheiten verwenden, z. B. `45 min` oder `1h 30m`",
+    invalid_locale: "Ungültige Locale '{}': erwartet wird ein Sprachcode wie `de` oder `de_AT`",
+    reference_not_found: "Referenziertes Rezept nicht gefunden: {}",
+    extension_disabled: "`{}` benötigt die Parser-Erweiterung `{}`, die deaktiviert ist",
+    unclosed_quantity: "Fehlendes `}`: die Menge wird als Text gelesen",
+    unclosed_note: "Fehlende `)`: die Notiz wird als Text gelesen",
+    unbraced_name: "Nur `{}` wird als Name gelesen: ein Name mit Leerzeichen endet mit `{}`",
+    quantity_separator: "`{}` wird als Text gelesen: Menge und Einheit mit `%` trennen",
+    decimal_comma: "`{}` wird als Text gelesen: einen Dezimalpunkt verwenden",
+    change_to: "Ändern in `{}`",
+    missing_quantity: "Keine Menge für `{}`",
+    unused_cookware: "`{}` ist deklariert, wird aber in keinem Schritt verwendet",
+    duplicate_ingredient: "`{}` ist bereits in Zeile {} definiert: `@&{}` verweist darauf",
+    first_defined_here: "Zuerst hier definiert",
+    long_step: "Der Schritt hat {} Sätze, mehr als {}: besser aufteilen",
+    missing_metadata: "Keine Metadaten `{}`",
+    failed_to_parse: "Rezept konnte nicht gelesen werden",
+};
+
+pub static FR: Strings = Strings {
+    ingredient: "Ingrédient",
+    cookware: "Ustensile",
+    timer: "Minuteur",
+    unnamed: "sans nom",
+    section: "Section",
+    metadata: "Métadonnées",
+    comment: "Commentaire",
+    recipe: "Recette",
+    recipe_not_found: "Recette introuvable",
+    servings: "Portions",
+    total_time: "Temps total",
+    ingredients: "Ingrédients",
+    quantity: "Quantité",
+    note: "Note",
+    duration: "Durée",
+    servings_not_a_number: "'{}' doit être un nombre entier de portions, par ex. `{}: 4`",
+    invalid_duration: "Impossible de lire '{}' comme durée pour '{}' : utilisez des minutes ou des unités, par ex. `45 min` ou `1h 30m`",
+    invalid_locale: "Locale invalide '{}' : un code de langue comme `fr` ou `fr_CA` est attendu",
+    reference_not_found: "Recette référencée introuvable : {}",
+    extension_disabled: "`{}` nécessite l'extension du parseur `{}`, qui est désactivée",
+    unclosed_quantity: "`}` manquante : la quantité est lue comme du texte",
+    unclosed_note: "`)` manquante : la note est lue comme du texte",
+    unbraced_name: "Seul `{}` est lu comme nom : un nom avec des espaces se termine par `{}`",
+    quantity_separator: "`{}` est lu comme du texte : séparez la quantité et l'unité par `%`",
+    decimal_comma: "`{}` est lu comme du texte : utilisez un point décimal",
+    change_to: "Remplacer par `{}`",
+    missing_quantity: "Aucune quantité pour `{}`",
+    unused_cookware: "`{}` est déclaré mais utilisé dans aucune étape",
+    duplicate_ingredient: "`{}` est déjà défini ligne {} : écrivez `@&{}` pour y faire référence",
+    first_defined_here: "Défini ici pour la première fois",
+    long_step: "L'étape compte {} phrases, plus de {} : pensez à la découper",
+    missing_metadata: "Métadonnée `{}` absente",
+    failed_to_parse: "Impossible d'analyser la recette",
+};
+
+pub static ES: Strings = Strings {
+    ingredient: "Ingrediente",
+    cookware: "Utensilio",
+    timer: "Temporizador",
+    unnamed: "sin nombre",
+    section: "Sección",
+    metadata: "Metadatos",
+    comment: "Comentario",
+    recipe: "Receta",
+    recipe_not_found: "Receta no encontrada",
+    servings: "Raciones",
+    total_time: "Tiempo total",
+    ingredients: "Ingredientes",
+    quantity: "Cantidad",
+    note: "Nota",
+    duration: "Duración",
+    servings_not_a_number: "'{}' debe ser un número entero de raciones, p. ej. `{}: 4`",
+    invalid_duration: "No se pudo leer '{}' como duración para '{}': usa minutos o unidades, p. ej. `45 min` o `1h 30m`",
+    invalid_locale: "Locale no válida '{}': se espera un código de idioma como `es` o `es_MX`",
+    reference_not_found: "Receta referenciada no encontrada: {}",
+    extension_disabled: "`{}` necesita la extensión del analizador `{}`, que está desactivada",
+    unclosed_quantity: "Falta `}`: la cantidad se lee como texto",
+    unclosed_note: "Falta `)`: la nota se lee como texto",
+    unbraced_name: "Solo `{}` se lee como nombre: un nombre con espacios termina en `{}`",
+    quantity_separator: "`{}` se lee como texto: separa la cantidad y la unidad con `%`",
+    decimal_comma: "`{}` se lee como texto: usa un punto decimal",
+    change_to: "Cambiar a `{}`",
+    missing_quantity: "Sin cantidad para `{}`",
+    unused_cookware: "`{}` está declarado pero no se usa en ningún paso",
+    duplicate_ingredient: "`{}` ya está definido en la línea {}: escribe `@&{}` para referirte a él",
+    first_defined_here: "Definido aquí por primera vez",
+    long_step: "El paso tiene {} frases, más de {}: considera dividirlo",
+    missing_metadata: "Falta el metadato `{}`",
+    failed_to_parse: "No se pudo analizar la receta",
+};
+
+/// Replace each `{}` in `template` with the next argument.
+pub fn fill(template: &str, args: &[&str]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut rest = template;
+    while let Some(at) = rest.find("{}") {
+        out.push_str(&rest[..at]);
+        out.push_str(args.next().copied().unwrap_or_default());
+        rest = &rest[at + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::Url;
+
+    #[test]
+    fn language_from_locale() {
+        assert_eq!(Language::from_locale("de_AT"), Some(Language::German));
+        assert_eq!(Language::from_locale("fr-CA"), Some(Language::French));
+        assert_eq!(Language::from_locale("ES"), Some(Language::Spanish));
+        assert_eq!(Language::from_locale("it"), None);
+
+        let doc = Document::new(
+            Url::parse("file:///test.cook").unwrap(),
+            1,
+            ">> locale: de\nMix @Mehl.".into(),
+        );
+        assert_eq!(Language::of_document(&doc), Some(Language::German));
+    }
+
+    #[test]
+    fn fills_placeholders() {
+        assert_eq!(
+            fill(DE.servings_not_a_number, &["portionen", "portionen"]),
+            "'portionen' sollte eine ganze Zahl von Portionen sein, z. B. `portionen: 4`"
+        );
+        assert_eq!(
+            fill(EN.reference_not_found, &[]),
+            "Referenced recipe not found: "
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! Index of the recipes in the workspace.
+//!
+//! Every `.cook` and `.menu` file under the workspace root is summarized
+//! once at startup; open documents are re-summarized as they are edited and
+//! files changed outside the editor as the client reports them.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use cooklang::{Quantity, Recipe};
+
+use crate::document::parse_recipe;
+
+/// How an ingredient or named timer is measured in a recipe, e.g.
+/// `@flour{200%g}` or `~rest{10%minutes}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuantityUse {
+    /// Lowercased ingredient or timer name
+    pub name: String,
+    /// The quantity value as written (`200`, `1/2`), if any
+    pub value: Option<String>,
+    pub unit: Option<String>,
+}
+
+impl QuantityUse {
+    fn new(name: &str, quantity: Option<&Quantity>) -> Self {
+        Self {
+            name: name.to_lowercase(),
+            value: quantity.map(|q| q.value().to_string()),
+            unit: quantity.and_then(|q| q.unit()).map(str::to_string),
+        }
+    }
+
+    /// The quantity as written in a component, `200%g` or `3`
+    pub fn quantity(&self) -> Option<String> {
+        let value = self.value.as_ref()?;
+        Some(match &self.unit {
+            Some(unit) => format!("{}%{}", value, unit),
+            None => value.clone(),
+        })
+    }
+}
+
+/// What the index keeps about one recipe.
+#[derive(Debug, Clone, Default)]
+pub struct IndexEntry {
+    /// Tags from the `tags` metadata
+    pub tags: Vec<String>,
+    /// Every ingredient in the recipe, except recipe references
+    pub ingredients: Vec<QuantityUse>,
+    /// Every named timer in the recipe
+    pub timers: Vec<QuantityUse>,
+}
+
+impl IndexEntry {
+    pub fn from_recipe(recipe: &Recipe) -> Self {
+        let tags = recipe
+            .metadata
+            .tags()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+
+        let ingredients = recipe
+            .ingredients
+            .iter()
+            .filter(|ingredient| ingredient.reference.is_none())
+            .map(|ingredient| QuantityUse::new(&ingredient.name, ingredient.quantity.as_ref()))
+            .collect();
+
+        let timers = recipe
+            .timers
+            .iter()
+            .filter_map(|timer| {
+                Some(QuantityUse::new(
+                    timer.name.as_ref()?,
+                    timer.quantity.as_ref(),
+                ))
+            })
+            .collect();
+
+        Self {
+            tags,
+            ingredients,
+            timers,
+        }
+    }
+
+    /// Summarize recipe source; unparseable recipes get an empty entry so
+    /// they still count as part of the workspace.
+    pub fn from_source(content: &str) -> Self {
+        parse_recipe(content)
+            .map(|recipe| Self::from_recipe(&recipe))
+            .unwrap_or_default()
+    }
+}
+
+/// Whether `path` is a file the index covers.
+pub fn is_recipe_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("cook" | "menu")
+    )
+}
+
+/// All recipe and menu files under `root`, skipping hidden directories.
+pub fn recipe_files(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    collect_files(root, &mut files);
+    files.sort();
+    files
+}
+
+/// The files at `paths`, with directories replaced by the recipe and menu
+/// files under them
+pub fn recipe_paths(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            files.extend(recipe_files(path));
+        } else if path.is_file() {
+            files.push(path.clone());
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{}: no such file or directory", path.display()),
+            ));
+        }
+    }
+    Ok(files)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            let hidden = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with('.'));
+            if !hidden {
+                collect_files(&path, files);
+            }
+        } else if is_recipe_file(&path) {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn entry_collects_tags_from_either_syntax() {
+        let entry = IndexEntry::from_source(">> tags: quick, vegan , \n\nMix @a.");
+        assert_eq!(entry.tags, vec!["quick", "vegan"]);
+
+        let entry = IndexEntry::from_source("---\ntags:\n  - soup\n  - winter\n---\nBoil @water.");
+        assert_eq!(entry.tags, vec!["soup", "winter"]);
+    }
+
+    #[test]
+    fn entry_records_ingredient_quantities() {
+        let entry = IndexEntry::from_source(
+            "Mix @Flour{200%g}, @eggs{3} and @salt. Serve with @./Pesto{}.",
+        );
+        let uses: Vec<_> = entry
+            .ingredients
+            .iter()
+            .map(|u| (u.name.as_str(), u.value.as_deref(), u.unit.as_deref()))
+            .collect();
+        assert_eq!(
+            uses,
+            vec![
+                ("flour", Some("200"), Some("g")),
+                ("eggs", Some("3"), None),
+                ("salt", None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn entry_records_named_timers() {
+        let entry = IndexEntry::from_source("Let it ~rest{10%minutes}, bake ~{25-30%min}.");
+        assert_eq!(
+            entry.timers,
+            vec![QuantityUse {
+                name: "rest".into(),
+                value: Some("10".into()),
+                unit: Some("minutes".into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn finds_recipe_files() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("sauces")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("Soup.cook"), "").unwrap();
+        fs::write(root.join("Week.menu"), "").unwrap();
+        fs::write(root.join("sauces/Pesto.cook"), "").unwrap();
+        fs::write(root.join(".git/Old.cook"), "").unwrap();
+        fs::write(root.join("notes.md"), "").unwrap();
+
+        let files = recipe_files(root);
+        assert_eq!(
+            files,
+            vec![
+                root.join("Soup.cook"),
+                root.join("Week.menu"),
+                root.join("sauces/Pesto.cook"),
+            ]
+        );
+    }
+}
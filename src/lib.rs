@@ -1,12 +1,27 @@
 mod backend;
+pub mod check;
 mod completion;
+mod data;
 mod diagnostics;
 mod document;
+pub mod format;
 mod hover;
+mod i18n;
+mod index;
+mod lints;
 pub mod lsp;
+mod menu;
+mod metadata;
+mod portability;
+mod project_config;
+mod quick_fixes;
+mod references;
 mod semantic_tokens;
+mod settings;
+mod shopping_list;
 mod state;
 mod symbols;
+mod templates;
 pub mod utils;
 
 pub use backend::Backend;
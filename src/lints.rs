@@ -0,0 +1,562 @@
+//! Lint rules: style and consistency checks on recipes that parse.
+//!
+//! Each rule has a name and a default severity. The name configures it (a
+//! level under `lints` in the settings or in `.cooklang.toml`), is the code
+//! of its diagnostics (linking to `docs/lints.md`) and suppresses it in a
+//! comment:
+//!
+//! ```cooklang
+//! -- cooklang-ignore: missing-quantity
+//! Season with @salt and @pepper.
+//! ```
+//!
+//! A comment on its own line covers the step after it, one at the end of a
+//! line covers that line, and `-- cooklang-ignore-file: <rules>` covers the
+//! whole recipe. Without rule names every rule is suppressed.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::str::FromStr;
+
+use cooklang::metadata::StdKey;
+use cooklang::parser::{BlockKind, Event, PullParser};
+use tower_lsp::lsp_types::{
+    CodeDescription, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location,
+    NumberOrString, Url,
+};
+
+use crate::document::Document;
+use crate::i18n::{fill, Strings};
+use crate::settings::LintSettings;
+use crate::utils::position::span_to_range;
+
+/// A lint rule
+#[derive(Debug)]
+pub struct Rule {
+    pub name: &'static str,
+    /// Severity when the settings don't set one
+    pub severity: DiagnosticSeverity,
+}
+
+/// An ingredient without a quantity, here or where it is referenced
+pub const MISSING_QUANTITY: Rule = Rule {
+    name: "missing-quantity",
+    severity: DiagnosticSeverity::INFORMATION,
+};
+
+/// Cookware declared in a `[mode]: components` list that no step refers to
+pub const UNUSED_COOKWARE: Rule = Rule {
+    name: "unused-cookware",
+    severity: DiagnosticSeverity::WARNING,
+};
+
+/// An ingredient defined again instead of referenced with `@&`
+pub const DUPLICATE_INGREDIENT: Rule = Rule {
+    name: "duplicate-ingredient",
+    severity: DiagnosticSeverity::INFORMATION,
+};
+
+/// A step with more sentences than the `maxStepSentences` setting
+pub const LONG_STEP: Rule = Rule {
+    name: "long-step",
+    severity: DiagnosticSeverity::INFORMATION,
+};
+
+/// No `servings` or `title` metadata
+pub const MISSING_METADATA: Rule = Rule {
+    name: "missing-metadata",
+    severity: DiagnosticSeverity::INFORMATION,
+};
+
+pub const RULES: &[Rule] = &[
+    MISSING_QUANTITY,
+    UNUSED_COOKWARE,
+    DUPLICATE_INGREDIENT,
+    LONG_STEP,
+    MISSING_METADATA,
+];
+
+pub fn is_rule(name: &str) -> bool {
+    RULES.iter().any(|rule| rule.name == name)
+}
+
+/// Where a rule is documented
+pub fn docs_url(rule: &Rule) -> Option<Url> {
+    let repository = env!("CARGO_PKG_REPOSITORY");
+    Url::parse(&format!(
+        "{repository}/blob/main/docs/lints.md#{}",
+        rule.name
+    ))
+    .ok()
+}
+
+/// A rule broken somewhere in the recipe
+struct Lint {
+    rule: &'static Rule,
+    span: Range<usize>,
+    message: String,
+    /// Another place involved, and what it is
+    related: Option<(Range<usize>, &'static str)>,
+}
+
+/// The lint diagnostics for `doc`
+pub fn check(doc: &Document, settings: &LintSettings, strings: &Strings) -> Vec<Diagnostic> {
+    let Some(ref result) = doc.parse_result else {
+        return Vec::new();
+    };
+    let recipe = &result.recipe;
+    let mut lints = Vec::new();
+    // Rules turned off aren't even computed
+    let enabled = |rule: &Rule| settings.rule(rule.name).is_enabled();
+
+    // The parsed ingredients and cookware are in source order, so they pair
+    // up with the components the pull parser finds (unless some were
+    // dropped, e.g. in `[mode]: text`)
+    let (ingredient_spans, cookware_spans) = component_spans(doc);
+    if ingredient_spans.len() == recipe.ingredients.len() {
+        let ingredients: Vec<_> = recipe.ingredients.iter().zip(ingredient_spans).collect();
+        if enabled(&MISSING_QUANTITY) {
+            missing_quantities(recipe, &ingredients, strings, &mut lints);
+        }
+        if enabled(&DUPLICATE_INGREDIENT) {
+            duplicate_ingredients(doc, &ingredients, strings, &mut lints);
+        }
+    }
+    if enabled(&UNUSED_COOKWARE) && cookware_spans.len() == recipe.cookware.len() {
+        for (cookware, span) in recipe.cookware.iter().zip(cookware_spans) {
+            if cookware.relation.is_defined_in_step() == Some(false)
+                && cookware.relation.referenced_from().is_empty()
+            {
+                lints.push(Lint {
+                    rule: &UNUSED_COOKWARE,
+                    span,
+                    message: fill(strings.unused_cookware, &[&cookware.name]),
+                    related: None,
+                });
+            }
+        }
+    }
+    if enabled(&LONG_STEP) {
+        long_steps(doc, settings.max_step_sentences, strings, &mut lints);
+    }
+    if enabled(&MISSING_METADATA) {
+        missing_metadata(doc, recipe, strings, &mut lints);
+    }
+
+    let suppressions = Suppressions::parse(&doc.content);
+    lints
+        .into_iter()
+        .filter_map(|lint| {
+            let level = settings.rule(lint.rule.name);
+            let range = span_to_range(lint.span.start, lint.span.end, &doc.line_index);
+            if suppressions.covers(lint.rule.name, range.start.line) {
+                return None;
+            }
+            let related_information = lint.related.map(|(span, message)| {
+                let range = span_to_range(span.start, span.end, &doc.line_index);
+                vec![DiagnosticRelatedInformation {
+                    location: Location::new(doc.uri.clone(), range),
+                    message: message.into(),
+                }]
+            });
+            Some(Diagnostic {
+                range,
+                severity: Some(level.severity().unwrap_or(lint.rule.severity)),
+                code: Some(NumberOrString::String(lint.rule.name.into())),
+                code_description: docs_url(lint.rule).map(|href| CodeDescription { href }),
+                source: Some("cooklang".into()),
+                message: lint.message,
+                related_information,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// Spans of the ingredients and of the cookware, in source order
+fn component_spans(doc: &Document) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let mut ingredients = Vec::new();
+    let mut cookware = Vec::new();
+    for event in PullParser::new(&doc.content, doc.extensions) {
+        match event {
+            Event::Ingredient(ingredient) => ingredients.push(ingredient.span().range()),
+            Event::Cookware(item) => cookware.push(item.span().range()),
+            _ => {}
+        }
+    }
+    (ingredients, cookware)
+}
+
+fn missing_quantities(
+    recipe: &cooklang::Recipe,
+    ingredients: &[(&cooklang::Ingredient, Range<usize>)],
+    strings: &Strings,
+    lints: &mut Vec<Lint>,
+) {
+    for (ingredient, span) in ingredients {
+        let quantified = ingredient.quantity.is_some()
+            || ingredient
+                .relation
+                .referenced_from()
+                .iter()
+                .any(|&i| recipe.ingredients[i].quantity.is_some());
+        if ingredient.relation.is_definition() && ingredient.reference.is_none() && !quantified {
+            lints.push(Lint {
+                rule: &MISSING_QUANTITY,
+                span: span.clone(),
+                message: fill(strings.missing_quantity, &[&ingredient.name]),
+                related: None,
+            });
+        }
+    }
+}
+
+fn duplicate_ingredients(
+    doc: &Document,
+    ingredients: &[(&cooklang::Ingredient, Range<usize>)],
+    strings: &Strings,
+    lints: &mut Vec<Lint>,
+) {
+    let mut definitions: HashMap<String, Range<usize>> = HashMap::new();
+    for (ingredient, span) in ingredients {
+        if !ingredient.relation.is_definition() || ingredient.reference.is_some() {
+            continue;
+        }
+        let Some(first) = definitions.get(&ingredient.name.to_lowercase()) else {
+            definitions.insert(ingredient.name.to_lowercase(), span.clone());
+            continue;
+        };
+        let (line, _) = doc.line_index.line_col(first.start as u32);
+        let line = (line + 1).to_string();
+        lints.push(Lint {
+            rule: &DUPLICATE_INGREDIENT,
+            span: span.clone(),
+            message: fill(
+                strings.duplicate_ingredient,
+                &[&ingredient.name, &line, &ingredient.name],
+            ),
+            related: Some((first.clone(), strings.first_defined_here)),
+        });
+    }
+}
+
+/// Steps with more than `max` sentences. The diagnostic covers the sentences
+/// past the limit.
+fn long_steps(doc: &Document, max: usize, strings: &Strings, lints: &mut Vec<Lint>) {
+    let content = doc.content.as_str();
+    let mut in_step = false;
+    // Where each sentence of the current step ends
+    let mut sentence_ends = Vec::new();
+    let mut step_end = 0;
+
+    for event in PullParser::new(content, doc.extensions) {
+        match event {
+            Event::Start(BlockKind::Step) => {
+                in_step = true;
+                sentence_ends.clear();
+            }
+            Event::Text(text) if in_step => {
+                let span = text.span().range();
+                for (i, c) in content[span.clone()].char_indices() {
+                    if !matches!(c, '.' | '!' | '?') {
+                        continue;
+                    }
+                    let end = span.start + i + c.len_utf8();
+                    if content[end..]
+                        .chars()
+                        .next()
+                        .is_none_or(char::is_whitespace)
+                    {
+                        sentence_ends.push(end);
+                    }
+                }
+                step_end = span.end;
+            }
+            Event::Ingredient(_) | Event::Cookware(_) | Event::Timer(_) if in_step => {
+                step_end = match event {
+                    Event::Ingredient(c) => c.span().end(),
+                    Event::Cookware(c) => c.span().end(),
+                    Event::Timer(c) => c.span().end(),
+                    _ => unreachable!(),
+                };
+            }
+            Event::End(BlockKind::Step) => {
+                in_step = false;
+                let last = sentence_ends.last().copied().unwrap_or(0);
+                let mut sentences = sentence_ends.len();
+                if !content[last.min(step_end)..step_end].trim().is_empty() {
+                    sentences += 1;
+                }
+                if sentences <= max || max == 0 {
+                    continue;
+                }
+                let rest = &content[sentence_ends[max - 1]..step_end];
+                let start = sentence_ends[max - 1] + (rest.len() - rest.trim_start().len());
+                let end = sentence_ends[max - 1] + rest.trim_end().len();
+                lints.push(Lint {
+                    rule: &LONG_STEP,
+                    span: start..end,
+                    message: fill(
+                        strings.long_step,
+                        &[&sentences.to_string(), &max.to_string()],
+                    ),
+                    related: None,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+fn missing_metadata(
+    doc: &Document,
+    recipe: &cooklang::Recipe,
+    strings: &Strings,
+    lints: &mut Vec<Lint>,
+) {
+    if doc.content.trim().is_empty() {
+        return;
+    }
+    let keys: Vec<StdKey> = recipe
+        .metadata
+        .map
+        .keys()
+        .filter_map(|key| StdKey::from_str(key.as_str()?).ok())
+        .collect();
+    // Where the metadata would go: the first line after any comments at the
+    // top, so a `-- cooklang-ignore` comment there covers it
+    let start = doc
+        .content
+        .split_inclusive('\n')
+        .take_while(|line| line.trim_start().starts_with("--"))
+        .map(str::len)
+        .sum::<usize>();
+    let line = doc.content[start..].lines().next().unwrap_or_default();
+    let span = start..start + line.trim_end().len();
+    for (key, name) in [(StdKey::Servings, "servings"), (StdKey::Title, "title")] {
+        if !keys.contains(&key) {
+            lints.push(Lint {
+                rule: &MISSING_METADATA,
+                span: span.clone(),
+                message: fill(strings.missing_metadata, &[name]),
+                related: None,
+            });
+        }
+    }
+}
+
+/// `-- cooklang-ignore` comments
+#[derive(Debug, Default)]
+struct Suppressions {
+    /// Rules suppressed in the whole file
+    file: Vec<Vec<String>>,
+    /// Rules suppressed on a range of lines
+    lines: Vec<(Range<u32>, Vec<String>)>,
+}
+
+impl Suppressions {
+    fn parse(content: &str) -> Self {
+        let mut suppressions = Self::default();
+        let lines: Vec<&str> = content.lines().collect();
+
+        for (n, line) in lines.iter().enumerate() {
+            let Some(at) = line.find("--") else {
+                continue;
+            };
+            let comment = line[at + 2..].trim();
+            let (whole_file, rest) = match comment.strip_prefix("cooklang-ignore-file") {
+                Some(rest) => (true, rest),
+                None => match comment.strip_prefix("cooklang-ignore") {
+                    Some(rest) => (false, rest),
+                    None => continue,
+                },
+            };
+            // No names: every rule
+            let rules: Vec<String> = match rest.trim() {
+                "" => Vec::new(),
+                rest => match rest.strip_prefix(':') {
+                    Some(names) => names
+                        .split(',')
+                        .map(|name| name.trim().to_string())
+                        .filter(|name| !name.is_empty())
+                        .collect(),
+                    None => continue,
+                },
+            };
+
+            let n = n as u32;
+            if whole_file {
+                suppressions.file.push(rules);
+            } else if line[..at].trim().is_empty() {
+                // The step after the comment, up to the next blank line
+                let end = lines[n as usize + 1..]
+                    .iter()
+                    .position(|line| line.trim().is_empty())
+                    .map_or(lines.len() as u32, |i| n + 1 + i as u32);
+                suppressions.lines.push((n + 1..end, rules));
+            } else {
+                suppressions.lines.push((n..n + 1, rules));
+            }
+        }
+        suppressions
+    }
+
+    fn covers(&self, rule: &str, line: u32) -> bool {
+        let names = |rules: &Vec<String>| rules.is_empty() || rules.iter().any(|r| r == rule);
+        self.file.iter().any(names)
+            || self
+                .lines
+                .iter()
+                .any(|(lines, rules)| lines.contains(&line) && names(rules))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::EN;
+    use crate::settings::LintLevel;
+
+    fn lints(content: &str, settings: &LintSettings) -> Vec<(String, u32, String)> {
+        let doc = Document::new(
+            Url::parse("file:///test.cook").unwrap(),
+            1,
+            content.to_string(),
+        );
+        check(&doc, settings, &EN)
+            .into_iter()
+            .map(|d| {
+                let Some(NumberOrString::String(code)) = d.code else {
+                    panic!("lint without a code");
+                };
+                (code, d.range.start.line, d.message)
+            })
+            .collect()
+    }
+
+    fn lint(code: &str, line: u32, message: &str) -> (String, u32, String) {
+        (code.into(), line, message.into())
+    }
+
+    const METADATA: &str = ">> title: Soup\n>> servings: 2\n";
+
+    #[test]
+    fn ingredients() {
+        let content = format!(
+            "{METADATA}Add @salt and @water{{1%l}}.\n\nStir in more @water{{250%ml}} and @&salt{{1%tsp}}. Top with @cress."
+        );
+        assert_eq!(
+            lints(&content, &LintSettings::default()),
+            vec![
+                lint("missing-quantity", 4, "No quantity for `cress`"),
+                lint(
+                    "duplicate-ingredient",
+                    4,
+                    "`water` is already defined on line 3: write `@&water` to refer to it"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn unused_cookware() {
+        let content = format!(
+            "{METADATA}>> [mode]: components\n#pot{{}} #sieve{{}}\n\n>> [mode]: steps\nBoil @water{{1%l}} in the #&pot."
+        );
+        assert_eq!(
+            lints(&content, &LintSettings::default()),
+            vec![lint(
+                "unused-cookware",
+                3,
+                "`sieve` is declared but never used in a step"
+            )]
+        );
+    }
+
+    #[test]
+    fn long_steps_and_metadata() {
+        let settings = LintSettings {
+            max_step_sentences: 2,
+            ..Default::default()
+        };
+        let content = "Boil @water{1%l}. Add @pasta{100%g}. Wait 2.5 minutes! Drain";
+        let found = lints(content, &settings);
+        assert_eq!(
+            found,
+            vec![
+                lint(
+                    "long-step",
+                    0,
+                    "Step has 4 sentences, more than 2: consider splitting it"
+                ),
+                lint("missing-metadata", 0, "No `servings` metadata"),
+                lint("missing-metadata", 0, "No `title` metadata"),
+            ]
+        );
+
+        let doc = Document::new(
+            Url::parse("file:///test.cook").unwrap(),
+            1,
+            content.to_string(),
+        );
+        let long_step = &check(&doc, &settings, &EN)[0];
+        assert_eq!(long_step.range.start.character, 37);
+        assert_eq!(
+            long_step.code_description.as_ref().unwrap().href.fragment(),
+            Some("long-step")
+        );
+    }
+
+    #[test]
+    fn accented_steps() {
+        let settings = LintSettings {
+            max_step_sentences: 1,
+            ..Default::default()
+        };
+        let content = format!(
+            "{METADATA}Make the crème brûlée with @eggs{{2}}. Chill it… then serve à table!"
+        );
+        assert_eq!(
+            lints(&content, &settings),
+            vec![lint(
+                "long-step",
+                2,
+                "Step has 2 sentences, more than 1: consider splitting it"
+            )]
+        );
+    }
+
+    #[test]
+    fn levels_and_suppressions() {
+        let content = format!(
+            "{METADATA}-- cooklang-ignore: missing-quantity\nAdd @salt\nand @pepper.\n\nAdd @oil. -- cooklang-ignore\nAdd @cress."
+        );
+        assert_eq!(
+            lints(&content, &LintSettings::default()),
+            vec![lint("missing-quantity", 7, "No quantity for `cress`")]
+        );
+
+        let content = "-- cooklang-ignore-file: missing-metadata\nAdd @salt{1%tsp}.";
+        assert!(lints(content, &LintSettings::default()).is_empty());
+        let content = "-- cooklang-ignore: missing-metadata\nAdd @salt{1%tsp}.";
+        assert!(lints(content, &LintSettings::default()).is_empty());
+        let content = "-- Soup\n-- cooklang-ignore: missing-metadata\nAdd @salt{1%tsp}.";
+        assert!(lints(content, &LintSettings::default()).is_empty());
+
+        let mut settings = LintSettings::default();
+        settings
+            .rules
+            .insert("missing-metadata".into(), LintLevel::Off);
+        settings
+            .rules
+            .insert("missing-quantity".into(), LintLevel::Error);
+        let doc = Document::new(
+            Url::parse("file:///test.cook").unwrap(),
+            1,
+            "Add @salt.".into(),
+        );
+        let diagnostics = check(&doc, &settings, &EN);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+    }
+}
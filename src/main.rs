@@ -1,10 +1,31 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
 use tower_lsp::{LspService, Server};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+use cooklang_language_server::check::{self, OutputFormat};
+use cooklang_language_server::format;
 use cooklang_language_server::Backend;
 
+const USAGE: &str = "\
+Usage: cooklang-lsp                 Run the language server over stdio
+       cooklang-lsp check [--format <FORMAT>] [--root <DIR>] <PATHS>...
+       cooklang-lsp format [--check] [--root <DIR>] <PATHS>...
+
+check   Check recipes (files, or directories with .cook and .menu files) with
+        the editor's diagnostics and lints. Exits with 1 if there are errors.
+format  Format recipes in place, like the editor does. With --check, print
+        what would change instead and exit with 1 if anything would.
+
+Options:
+  --format <FORMAT>  human (default), json or sarif
+  --check            Don't write the files, show the diff
+  --root <DIR>       Workspace root, with .cooklang.toml (default: current directory)
+";
+
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     // Initialize logging to stderr (stdout is for LSP communication)
     tracing_subscriber::registry()
         .with(
@@ -15,12 +36,139 @@ async fn main() {
         .with(tracing_subscriber::EnvFilter::from_default_env())
         .init();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => {
+            serve().await;
+            ExitCode::SUCCESS
+        }
+        Some("check") => run_check(&args[1..]),
+        Some("format") => run_format(&args[1..]),
+        Some("-h" | "--help" | "help") => {
+            print!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Some(arg) => usage_error(&format!("unknown command `{arg}`")),
+    }
+}
+
+async fn serve() {
     tracing::info!("Starting Cooklang Language Server");
 
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(Backend::new);
+    let (service, socket) = LspService::build(Backend::new)
+        .custom_method("cooklang/tags", Backend::tags)
+        .finish();
 
     Server::new(stdin, stdout, socket).serve(service).await;
 }
+
+fn run_check(args: &[String]) -> ExitCode {
+    let mut format = OutputFormat::default();
+    let mut root = None;
+    let mut paths = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().map(|value| value.parse()) {
+                Some(Ok(value)) => format = value,
+                Some(Err(err)) => return usage_error(&err),
+                None => return usage_error("`--format` needs a value"),
+            },
+            "--root" => match args.next() {
+                Some(value) => root = Some(PathBuf::from(value)),
+                None => return usage_error("`--root` needs a value"),
+            },
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            option if option.starts_with('-') => {
+                return usage_error(&format!("unknown option `{option}`"))
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+    if paths.is_empty() {
+        return usage_error("no paths to check");
+    }
+    let root = root.unwrap_or_else(|| PathBuf::from("."));
+
+    match check::check(&root, &paths) {
+        Ok(reports) => {
+            print!("{}", check::render(&reports, format));
+            if check::has_errors(&reports) {
+                ExitCode::FAILURE
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run_format(args: &[String]) -> ExitCode {
+    let mut check = false;
+    let mut root = None;
+    let mut paths = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--root" => match args.next() {
+                Some(value) => root = Some(PathBuf::from(value)),
+                None => return usage_error("`--root` needs a value"),
+            },
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            option if option.starts_with('-') => {
+                return usage_error(&format!("unknown option `{option}`"))
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+    if paths.is_empty() {
+        return usage_error("no paths to format");
+    }
+    let root = root.unwrap_or_else(|| PathBuf::from("."));
+
+    let changed = match format::format_files(&root, &paths) {
+        Ok(changed) => changed,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    if check {
+        for file in &changed {
+            print!("{}", format::diff(file));
+        }
+        return if changed.is_empty() {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        };
+    }
+    for file in &changed {
+        if let Err(err) = std::fs::write(&file.path, &file.formatted) {
+            eprintln!("error: {}: {err}", file.path.display());
+            return ExitCode::from(2);
+        }
+        println!("Formatted {}", file.path.display());
+    }
+    ExitCode::SUCCESS
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("error: {message}\n\n{USAGE}");
+    ExitCode::from(2)
+}
@@ -0,0 +1,139 @@
+//! Menu files (`.menu`).
+//!
+//! A menu is parsed like any other Cooklang document; its steps reference
+//! recipes (`@./Dinners/Lasagna{4%servings}`), usually grouped into one
+//! section per day or meal (`== Monday ==`).
+
+use cooklang::parser::{Event, PullParser};
+use cooklang::Extensions;
+use tower_lsp::lsp_types::{DocumentSymbol, SymbolKind, Url};
+
+use crate::document::Document;
+use crate::references::located_references;
+use crate::utils::line_index::LineIndex;
+use crate::utils::position::span_to_range;
+
+/// Whether the document is a menu rather than a single recipe.
+pub fn is_menu(uri: &Url) -> bool {
+    uri.path().ends_with(".menu")
+}
+
+/// A named section header (`== Monday ==`) and the byte range of its line.
+struct SectionHeader {
+    name: String,
+    line_start: usize,
+    line_end: usize,
+}
+
+fn section_headers(content: &str, line_index: &LineIndex) -> Vec<SectionHeader> {
+    PullParser::new(content, Extensions::all())
+        .filter_map(|event| match event {
+            Event::Section { name: Some(name) } => {
+                let (line, _) = line_index.line_col(name.span().start() as u32);
+                let range = line_index.line_range(line);
+                Some(SectionHeader {
+                    name: name.text_trimmed().into_owned(),
+                    line_start: range.start as usize,
+                    line_end: content[..range.end as usize].trim_end().len(),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Outline of a menu: one symbol per section (day or meal) holding the
+/// recipes it references. The ingredients they add up to are what the
+/// shopping list command is for.
+#[allow(deprecated)] // DocumentSymbol::deprecated is deprecated but required
+pub fn get_menu_symbols(doc: &Document) -> Vec<DocumentSymbol> {
+    let line_index = &doc.line_index;
+    let headers = section_headers(&doc.content, line_index);
+
+    let mut symbols = Vec::new();
+    // References before the first header go at the top level.
+    let mut sections: Vec<(Option<&SectionHeader>, Vec<DocumentSymbol>)> = vec![(None, vec![])];
+    sections.extend(headers.iter().map(|h| (Some(h), vec![])));
+
+    for (span, ingredient) in located_references(doc) {
+        let section = headers
+            .iter()
+            .rposition(|h| h.line_start <= span.start())
+            .map_or(0, |i| i + 1);
+        let range = span_to_range(span.start(), span.end(), line_index);
+        let name = ingredient
+            .reference
+            .as_ref()
+            .map(|r| r.path("/"))
+            .unwrap_or_else(|| ingredient.name.clone());
+
+        sections[section].1.push(DocumentSymbol {
+            name,
+            kind: SymbolKind::FILE,
+            range,
+            selection_range: range,
+            detail: ingredient.quantity.as_ref().map(|q| q.to_string()),
+            children: None,
+            tags: None,
+            deprecated: None,
+        });
+    }
+
+    for (i, (header, children)) in sections.into_iter().enumerate() {
+        let Some(header) = header else {
+            symbols.extend(children);
+            continue;
+        };
+        // A section runs until the next header (or the end of the menu).
+        let end = headers
+            .get(i)
+            .map(|next| next.line_start)
+            .unwrap_or(doc.content.len());
+        let end = doc.content[..end].trim_end().len().max(header.line_end);
+
+        symbols.push(DocumentSymbol {
+            name: header.name.clone(),
+            kind: SymbolKind::NAMESPACE,
+            range: span_to_range(header.line_start, end, line_index),
+            selection_range: span_to_range(header.line_start, header.line_end, line_index),
+            detail: Some(match children.len() {
+                1 => "1 recipe".into(),
+                n => format!("{n} recipes"),
+            }),
+            children: Some(children),
+            tags: None,
+            deprecated: None,
+        });
+    }
+
+    symbols
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menu_outline_groups_references_by_day() {
+        let content = "== Monday ==\nBreakfast: @./Pancakes{4%servings}\n\n\
+                       == Tuesday ==\nLunch: @./Soup{}\nDinner: @./Pancakes{2%servings}\n";
+        let uri = Url::parse("file:///recipes/Week.menu").unwrap();
+        assert!(is_menu(&uri));
+        let doc = Document::new(uri, 1, content.to_string());
+        let symbols = get_menu_symbols(&doc);
+        let names: Vec<_> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Monday", "Tuesday"]);
+        assert_eq!(symbols[0].detail.as_deref(), Some("1 recipe"));
+        assert_eq!(symbols[1].detail.as_deref(), Some("2 recipes"));
+
+        let monday = symbols[0].children.as_ref().unwrap();
+        assert_eq!(monday.len(), 1);
+        assert_eq!(monday[0].name, "./Pancakes");
+        assert_eq!(monday[0].detail.as_deref(), Some("4 servings"));
+        assert_eq!(symbols[0].range.start.line, 0);
+        assert_eq!(symbols[0].range.end.line, 1);
+
+        let tuesday = symbols[1].children.as_ref().unwrap();
+        assert_eq!(tuesday.len(), 2);
+    }
+}
@@ -0,0 +1,227 @@
+//! Canonical metadata (`>> key: value` lines and YAML front matter).
+//!
+//! The parser already warns when a canonical key has a value it can't use,
+//! but only generically ("Unsupported value for key") and, in front matter,
+//! without pointing at the value. The common keys are checked here so the
+//! message says what is wrong and the range covers the offending value.
+
+use std::ops::Range;
+use std::str::FromStr;
+
+use cooklang::metadata::{CooklangValueExt, StdKey};
+use cooklang::parser::{Event, PullParser};
+
+use cooklang::{Extensions, Text};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity};
+
+use crate::document::{parser, Document};
+use crate::i18n::{fill, Strings};
+use crate::utils::position::span_to_range;
+
+/// A `key: value` metadata entry located in the source.
+#[derive(Debug, Clone)]
+pub struct MetadataEntry {
+    pub key: String,
+    pub key_span: Range<usize>,
+    /// The value as written (trimmed); empty for multi-line YAML values
+    pub value: String,
+    pub value_span: Range<usize>,
+}
+
+/// Byte range of the YAML front matter body (between the `---` lines), if
+/// the document starts with one. An unterminated front matter runs to the
+/// end of the document.
+pub fn front_matter_range(content: &str) -> Option<Range<usize>> {
+    let first_line = content.lines().next()?;
+    if first_line.trim_end() != "---" {
+        return None;
+    }
+
+    let start = content.find('\n')? + 1;
+    let mut offset = start;
+    for line in content[start..].split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some(start..offset);
+        }
+        offset += line.len();
+    }
+    Some(start..content.len())
+}
+
+/// Every metadata entry in the document, from the front matter (top-level
+/// keys only) and from `>>` lines.
+pub fn metadata_entries(content: &str) -> Vec<MetadataEntry> {
+    let mut entries = Vec::new();
+
+    if let Some(range) = front_matter_range(content) {
+        let mut offset = range.start;
+        for line in content[range.clone()].split_inclusive('\n') {
+            let line_start = offset;
+            offset += line.len();
+
+            // Indented lines belong to a nested value
+            if line.starts_with(char::is_whitespace) || line.starts_with('#') {
+                continue;
+            }
+            let Some(colon) = line.find(':') else {
+                continue;
+            };
+            let key = line[..colon].trim_end();
+            let raw_value = &line[colon + 1..];
+            let value = raw_value.trim();
+            let value_start =
+                line_start + colon + 1 + (raw_value.len() - raw_value.trim_start().len());
+
+            entries.push(MetadataEntry {
+                key: key.to_string(),
+                key_span: line_start..line_start + key.len(),
+                value: value.to_string(),
+                value_span: value_start..value_start + value.len(),
+            });
+        }
+    }
+
+    for event in PullParser::new(content, Extensions::all()) {
+        if let Event::Metadata { key, value } = event {
+            entries.push(MetadataEntry {
+                key: key.text_trimmed().into_owned(),
+                key_span: trimmed_span(&key),
+                value: value.text_trimmed().into_owned(),
+                value_span: trimmed_span(&value),
+            });
+        }
+    }
+
+    entries
+}
+
+/// Span of `text` without surrounding whitespace (a `>>` value runs to the
+/// end of the line, newline included).
+fn trimmed_span(text: &Text) -> Range<usize> {
+    let full = text.text();
+    let start = text.span().start() + (full.len() - full.trim_start().len());
+    start..start + full.trim().len()
+}
+
+/// Check the values of canonical keys. Each diagnostic is returned with the
+/// span of the entry it is about, so the parser's generic warning for it can
+/// be dropped.
+pub fn validate(doc: &Document, strings: &Strings) -> Vec<(Range<usize>, Diagnostic)> {
+    let Some(ref result) = doc.parse_result else {
+        return Vec::new();
+    };
+    let metadata = &result.recipe.metadata;
+    let parser = parser();
+    let converter = parser.converter();
+
+    metadata_entries(&doc.content)
+        .into_iter()
+        .filter_map(|entry| {
+            let std_key = StdKey::from_str(&entry.key).ok()?;
+            let value = metadata.map.get(entry.key.as_str())?;
+
+            let message = match std_key {
+                // `>>` values are always strings
+                StdKey::Servings
+                    if value.as_u32().is_none()
+                        && value.as_str().and_then(|s| s.parse::<u32>().ok()).is_none() =>
+                {
+                    fill(strings.servings_not_a_number, &[&entry.key, &entry.key])
+                }
+                StdKey::Time if value.as_time(converter).is_none() => {
+                    fill(strings.invalid_duration, &[&entry.value, &entry.key])
+                }
+                StdKey::PrepTime | StdKey::CookTime if value.as_minutes(converter).is_none() => {
+                    fill(strings.invalid_duration, &[&entry.value, &entry.key])
+                }
+                StdKey::Locale if value.as_locale().is_none() => {
+                    fill(strings.invalid_locale, &[&entry.value])
+                }
+                _ => return None,
+            };
+
+            let span = if entry.value_span.is_empty() {
+                entry.key_span.clone()
+            } else {
+                entry.value_span.clone()
+            };
+
+            let entry_span = entry.key_span.start..entry.value_span.end.max(entry.key_span.end);
+            Some((
+                entry_span,
+                Diagnostic {
+                    range: span_to_range(span.start, span.end, &doc.line_index),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    source: Some("cooklang".into()),
+                    message,
+                    ..Default::default()
+                },
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::Url;
+
+    fn validate_src(content: &str) -> Vec<(Range<usize>, Diagnostic)> {
+        let doc = Document::new(
+            Url::parse("file:///test.cook").unwrap(),
+            1,
+            content.to_string(),
+        );
+        validate(&doc, &crate::i18n::EN)
+    }
+
+    #[test]
+    fn locates_front_matter_and_line_entries() {
+        let content = "---\ntitle: Soup\ntags:\n  - quick\n---\nBoil @water.";
+        let entries = metadata_entries(content);
+        let keys: Vec<_> = entries.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, vec!["title", "tags"]);
+        assert_eq!(&content[entries[0].value_span.clone()], "Soup");
+        assert_eq!(entries[1].value, "");
+
+        let content = ">> servings: 4\nBoil @water.";
+        let entries = metadata_entries(content);
+        assert_eq!(&content[entries[0].key_span.clone()], "servings");
+        assert_eq!(&content[entries[0].value_span.clone()], "4");
+    }
+
+    #[test]
+    fn front_matter_range_requires_leading_delimiter() {
+        assert_eq!(front_matter_range("---\na: 1\n---\nstep"), Some(4..9));
+        assert_eq!(front_matter_range("---\na: 1\n"), Some(4..9));
+        assert_eq!(front_matter_range("Mix @a.\n---\n"), None);
+    }
+
+    #[test]
+    fn flags_malformed_values() {
+        let content = "---\nservings: four\nprep time: a while\ncook time: 1h 30m\nlocale: english\n---\nMix @a.";
+        let diags = validate_src(content);
+        let entries: Vec<_> = diags
+            .iter()
+            .map(|(span, _)| &content[span.clone()])
+            .collect();
+        assert_eq!(
+            entries,
+            vec!["servings: four", "prep time: a while", "locale: english"]
+        );
+
+        let (_, servings) = &diags[0];
+        assert!(servings.message.contains("whole number"), "{servings:?}");
+        // The range covers the value, not just the key position.
+        assert_eq!(servings.range.start.line, 1);
+        assert_eq!(servings.range.start.character, 10);
+        assert_eq!(servings.range.end.character, 14);
+    }
+
+    #[test]
+    fn accepts_valid_values() {
+        let content = ">> servings: 4\n>> time: 1h 30min\n>> locale: en_GB\nMix @a.";
+        let diags = validate_src(content);
+        assert!(diags.is_empty(), "{diags:?}");
+    }
+}
@@ -0,0 +1,178 @@
+//! Portability checks: syntax that needs a parser extension the workspace
+//! has disabled.
+//!
+//! With an extension off, the parser doesn't reject the syntax it adds; it
+//! just reads it differently (`@flour|farina{}` becomes an ingredient named
+//! "flour|farina"). The recipe is read again with every extension enabled
+//! to find where it relies on one, so recipes meant for stricter parsers
+//! (e.g. apps without extensions) stay portable.
+
+use std::ops::Range;
+
+use cooklang::parser::{Event, PullParser, Quantity};
+use cooklang::quantity::Value;
+use cooklang::{Extensions, Located, Span};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity};
+
+use crate::document::Document;
+use crate::i18n::{fill, Strings};
+use crate::utils::position::span_to_range;
+
+/// A use of extension syntax: where it is and the extension it needs
+type Use = (Range<usize>, Extensions, &'static str);
+
+/// Warnings for syntax in `doc` that needs an extension it is not parsed
+/// with.
+pub fn diagnostics(doc: &Document, strings: &Strings) -> Vec<Diagnostic> {
+    if doc.extensions.contains(Extensions::all()) {
+        return Vec::new();
+    }
+
+    extension_uses(&doc.content)
+        .into_iter()
+        .filter(|(_, extension, _)| !doc.extensions.contains(*extension))
+        .map(|(span, _, name)| {
+            let syntax = doc.content[span.clone()].trim();
+            Diagnostic {
+                range: span_to_range(span.start, span.end, &doc.line_index),
+                severity: Some(DiagnosticSeverity::WARNING),
+                source: Some("cooklang".into()),
+                message: fill(strings.extension_disabled, &[syntax, name]),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Every use of extension syntax in `content`
+fn extension_uses(content: &str) -> Vec<Use> {
+    let mut uses = Vec::new();
+
+    for event in PullParser::new(content, Extensions::all()) {
+        match event {
+            Event::Metadata { key, .. } => {
+                let text = key.text_trimmed();
+                if text.starts_with('[') && text.ends_with(']') {
+                    uses.push((span(key.span()), Extensions::MODES, "MODES"));
+                }
+            }
+            Event::Ingredient(ingredient) => {
+                if let Some(ref data) = ingredient.intermediate_data {
+                    let start = ingredient.modifiers.span().start();
+                    uses.push((
+                        start..data.span().end(),
+                        Extensions::INTERMEDIATE_PREPARATIONS,
+                        "INTERMEDIATE_PREPARATIONS",
+                    ));
+                } else if !ingredient.modifiers.is_empty() {
+                    uses.push(modifiers_use(ingredient.modifiers.span()));
+                }
+                if let Some(ref alias) = ingredient.alias {
+                    uses.push(alias_use(alias.span()));
+                }
+                quantity_uses(content, ingredient.quantity.as_ref(), &mut uses);
+            }
+            Event::Cookware(cookware) => {
+                if !cookware.modifiers.is_empty() {
+                    uses.push(modifiers_use(cookware.modifiers.span()));
+                }
+                if let Some(ref alias) = cookware.alias {
+                    uses.push(alias_use(alias.span()));
+                }
+                quantity_uses(content, cookware.quantity.as_ref(), &mut uses);
+            }
+            Event::Timer(timer) => {
+                quantity_uses(content, timer.quantity.as_ref(), &mut uses);
+            }
+            _ => {}
+        }
+    }
+
+    uses
+}
+
+fn span(span: Span) -> Range<usize> {
+    span.start()..span.end()
+}
+
+fn modifiers_use(modifiers: Span) -> Use {
+    (
+        span(modifiers),
+        Extensions::COMPONENT_MODIFIERS,
+        "COMPONENT_MODIFIERS",
+    )
+}
+
+/// The alias span doesn't include the `|`
+fn alias_use(alias: Span) -> Use {
+    (
+        alias.start().saturating_sub(1)..alias.end(),
+        Extensions::COMPONENT_ALIAS,
+        "COMPONENT_ALIAS",
+    )
+}
+
+/// Ranges (`2-3`) and units without a `%` (`10 kg`)
+fn quantity_uses(content: &str, quantity: Option<&Located<Quantity>>, uses: &mut Vec<Use>) {
+    let Some(quantity) = quantity else {
+        return;
+    };
+    if let Value::Range { .. } = *quantity.value.value {
+        uses.push((
+            span(quantity.value.value.span()),
+            Extensions::RANGE_VALUES,
+            "RANGE_VALUES",
+        ));
+    }
+    if quantity.unit.is_some() && !content[span(quantity.span())].contains('%') {
+        uses.push((
+            span(quantity.span()),
+            Extensions::ADVANCED_UNITS,
+            "ADVANCED_UNITS",
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::EN;
+    use tower_lsp::lsp_types::Url;
+
+    fn warnings(content: &str, extensions: Extensions) -> Vec<String> {
+        let doc = Document::with_extensions(
+            Url::parse("file:///test.cook").unwrap(),
+            1,
+            content.to_string(),
+            extensions,
+        );
+        diagnostics(&doc, &EN)
+            .into_iter()
+            .map(|d| d.message)
+            .collect()
+    }
+
+    #[test]
+    fn flags_syntax_of_disabled_extensions() {
+        let content = ">> [mode]: ingredients\nMix @?flour|farina{2-3%cups}, @&(=1)dough{} and #pot{} for ~{10 min}.";
+        let messages = warnings(content, Extensions::empty());
+        assert_eq!(
+            messages,
+            vec![
+                "`[mode]` needs the `MODES` parser extension, which is disabled",
+                "`?` needs the `COMPONENT_MODIFIERS` parser extension, which is disabled",
+                "`|farina` needs the `COMPONENT_ALIAS` parser extension, which is disabled",
+                "`2-3` needs the `RANGE_VALUES` parser extension, which is disabled",
+                "`&(=1)` needs the `INTERMEDIATE_PREPARATIONS` parser extension, which is disabled",
+                "`10 min` needs the `ADVANCED_UNITS` parser extension, which is disabled",
+            ]
+        );
+
+        // Only the disabled ones
+        let messages = warnings(content, Extensions::all() ^ Extensions::COMPONENT_ALIAS);
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("COMPONENT_ALIAS"));
+
+        assert!(warnings(content, Extensions::all()).is_empty());
+    }
+}
@@ -0,0 +1,600 @@
+//! Project configuration: `.cooklang.toml` in the workspace root.
+//!
+//! The file is checked in with the recipes so everyone working on them gets
+//! the same parser extensions, lints, configuration files and formatting,
+//! whatever their editor. Every key is optional; the ones the file sets
+//! override the editor's [settings](crate::settings), the others are left to
+//! them. Paths are relative to the workspace root.
+//!
+//! ```toml
+//! extensions = "compat"
+//! aisle-path = "shopping/aisle.conf"
+//! pantry-path = "shopping/pantry.conf"
+//! data-directory = ".cooklang"
+//! template-directory = ".cooklang/templates"
+//! locale = "de"
+//!
+//! [lints]
+//! metadata = "error"
+//! portability = "off"
+//! missing-quantity = "warning"
+//! max-step-sentences = 8
+//!
+//! [completion]
+//! common = false
+//!
+//! [format]
+//! section-style = "double"
+//! ```
+//!
+//! Problems in the file are reported as diagnostics on it, in English like
+//! the TOML parser's own messages. Keys that are unknown or have an invalid
+//! value are skipped, the others still apply; a file that isn't valid TOML
+//! sets nothing.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::de::{DeserializeOwned, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use toml::Spanned;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity};
+
+use crate::lints;
+use crate::settings::{ExtensionsSetting, LintLevel, SectionStyle, Settings};
+use crate::utils::line_index::LineIndex;
+use crate::utils::position::span_to_range;
+
+/// Name of the file in the workspace root
+pub const FILE_NAME: &str = ".cooklang.toml";
+
+#[derive(Debug, Default)]
+pub struct ProjectConfig {
+    extensions: Option<Spanned<ExtensionsSetting>>,
+    aisle_path: Option<Spanned<PathBuf>>,
+    pantry_path: Option<Spanned<PathBuf>>,
+    data_directory: Option<Spanned<PathBuf>>,
+    template_directory: Option<Spanned<PathBuf>>,
+    locale: Option<String>,
+    lints: LintConfig,
+    completion: CompletionConfig,
+    format: FormatConfig,
+}
+
+#[derive(Debug, Default)]
+struct LintConfig {
+    metadata: Option<LintLevel>,
+    references: Option<LintLevel>,
+    portability: Option<LintLevel>,
+    max_step_sentences: Option<usize>,
+    /// Levels of the lint rules
+    rules: BTreeMap<String, LintLevel>,
+}
+
+#[derive(Debug, Default)]
+struct CompletionConfig {
+    workspace: Option<bool>,
+    aisle: Option<bool>,
+    common: Option<bool>,
+}
+
+#[derive(Debug, Default)]
+struct FormatConfig {
+    section_style: Option<SectionStyle>,
+    compact_quantities: Option<bool>,
+}
+
+/// Something wrong in the file, and where
+type Problem = (Range<usize>, DiagnosticSeverity, String);
+
+/// The entries of a TOML table, with the spans of keys and values
+type Entries = Vec<(Spanned<String>, Spanned<Node>)>;
+
+/// A TOML value that keeps the spans inside its tables, so each key can be
+/// read (or reported) on its own
+#[derive(Debug)]
+enum Node {
+    Table(Entries),
+    Value(toml::Value),
+}
+
+impl Node {
+    fn into_value(self) -> toml::Value {
+        match self {
+            Self::Table(entries) => toml::Value::Table(
+                entries
+                    .into_iter()
+                    .map(|(key, node)| (key.into_inner(), node.into_inner().into_value()))
+                    .collect(),
+            ),
+            Self::Value(value) => value,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NodeVisitor;
+
+        impl<'de> Visitor<'de> for NodeVisitor {
+            type Value = Node;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a TOML value")
+            }
+
+            fn visit_bool<E>(self, value: bool) -> Result<Node, E> {
+                Ok(Node::Value(value.into()))
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<Node, E> {
+                Ok(Node::Value(value.into()))
+            }
+
+            fn visit_f64<E>(self, value: f64) -> Result<Node, E> {
+                Ok(Node::Value(value.into()))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Node, E> {
+                Ok(Node::Value(value.into()))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+                let mut values = Vec::new();
+                while let Some(value) = seq.next_element()? {
+                    values.push(value);
+                }
+                Ok(Node::Value(toml::Value::Array(values)))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(key) = map.next_key()? {
+                    entries.push((key, map.next_value()?));
+                }
+                Ok(Node::Table(entries))
+            }
+        }
+
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+/// The value of `node` as a `T`, or `None` and the reason it isn't one
+fn read<T: DeserializeOwned>(
+    node: Spanned<Node>,
+    problems: &mut Vec<Problem>,
+) -> Option<Spanned<T>> {
+    let span = node.span();
+    match node.into_inner().into_value().try_into::<T>() {
+        Ok(value) => Some(Spanned::new(span, value)),
+        Err(err) => {
+            problems.push((span, DiagnosticSeverity::ERROR, err.message().to_string()));
+            None
+        }
+    }
+}
+
+/// The entries of the table `node`, or none if it isn't a table
+fn entries(node: Spanned<Node>, problems: &mut Vec<Problem>) -> Entries {
+    let span = node.span();
+    match node.into_inner() {
+        Node::Table(entries) => entries,
+        Node::Value(value) => {
+            let message = format!("invalid type: {}, expected a table", value.type_str());
+            problems.push((span, DiagnosticSeverity::ERROR, message));
+            Vec::new()
+        }
+    }
+}
+
+fn unknown(key: &Spanned<String>, what: &str, problems: &mut Vec<Problem>) {
+    let message = format!("unknown {what} `{}`", key.get_ref());
+    problems.push((key.span(), DiagnosticSeverity::ERROR, message));
+}
+
+/// A project configuration file and the problems found in it
+#[derive(Debug)]
+pub struct ProjectFile {
+    pub path: PathBuf,
+    pub config: ProjectConfig,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ProjectConfig {
+    /// Read the keys of the file's top-level table, skipping the ones that
+    /// are unknown or invalid
+    fn read(table: Entries, problems: &mut Vec<Problem>) -> Self {
+        let mut config = Self::default();
+        for (key, node) in table {
+            match key.get_ref().as_str() {
+                "extensions" => config.extensions = read(node, problems),
+                "aisle-path" => config.aisle_path = read(node, problems),
+                "pantry-path" => config.pantry_path = read(node, problems),
+                "data-directory" => config.data_directory = read(node, problems),
+                "template-directory" => config.template_directory = read(node, problems),
+                "locale" => config.locale = read(node, problems).map(Spanned::into_inner),
+                "lints" => config.lints = LintConfig::read(entries(node, problems), problems),
+                "completion" => {
+                    let completion = &mut config.completion;
+                    for (key, node) in entries(node, problems) {
+                        let source = match key.get_ref().as_str() {
+                            "workspace" => &mut completion.workspace,
+                            "aisle" => &mut completion.aisle,
+                            "common" => &mut completion.common,
+                            _ => {
+                                unknown(&key, "key", problems);
+                                continue;
+                            }
+                        };
+                        *source = read(node, problems).map(Spanned::into_inner);
+                    }
+                }
+                "format" => {
+                    for (key, node) in entries(node, problems) {
+                        match key.get_ref().as_str() {
+                            "section-style" => {
+                                config.format.section_style =
+                                    read(node, problems).map(Spanned::into_inner)
+                            }
+                            "compact-quantities" => {
+                                config.format.compact_quantities =
+                                    read(node, problems).map(Spanned::into_inner)
+                            }
+                            _ => unknown(&key, "key", problems),
+                        }
+                    }
+                }
+                _ => unknown(&key, "key", problems),
+            }
+        }
+        config
+    }
+
+    /// Override `settings` with what the file sets
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(ref extensions) = self.extensions {
+            settings.extensions = Some(extensions.get_ref().clone());
+        }
+        if let Some(ref path) = self.aisle_path {
+            settings.aisle_path = Some(path.get_ref().clone());
+        }
+        if let Some(ref path) = self.pantry_path {
+            settings.pantry_path = Some(path.get_ref().clone());
+        }
+        if let Some(ref path) = self.data_directory {
+            settings.data_directory = Some(path.get_ref().clone());
+        }
+        if let Some(ref path) = self.template_directory {
+            settings.template_directory = Some(path.get_ref().clone());
+        }
+        if let Some(ref locale) = self.locale {
+            settings.locale = Some(locale.clone());
+        }
+
+        let lints = &mut settings.lints;
+        let levels = [
+            (&mut lints.metadata, self.lints.metadata),
+            (&mut lints.references, self.lints.references),
+            (&mut lints.portability, self.lints.portability),
+        ];
+        for (setting, level) in levels {
+            if let Some(level) = level {
+                *setting = level;
+            }
+        }
+        if let Some(max) = self.lints.max_step_sentences {
+            lints.max_step_sentences = max;
+        }
+        lints.rules.extend(
+            self.lints
+                .rules
+                .iter()
+                .map(|(name, level)| (name.clone(), *level)),
+        );
+
+        let completion = &mut settings.completion;
+        let sources = [
+            (&mut completion.workspace, self.completion.workspace),
+            (&mut completion.aisle, self.completion.aisle),
+            (&mut completion.common, self.completion.common),
+        ];
+        for (setting, enabled) in sources {
+            if let Some(enabled) = enabled {
+                *setting = enabled;
+            }
+        }
+
+        if let Some(style) = self.format.section_style {
+            settings.format.section_style = style;
+        }
+        if let Some(compact) = self.format.compact_quantities {
+            settings.format.compact_quantities = compact;
+        }
+    }
+
+    /// Values that parse but don't work in this workspace: unknown
+    /// extensions and paths that don't exist
+    fn problems(&self, workspace_root: &Path) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        if let Some(ref extensions) = self.extensions {
+            let (_, unknown) = extensions.get_ref().resolve();
+            let message = |name: &str| match extensions.get_ref() {
+                ExtensionsSetting::Preset(_) => format!(
+                    "unknown extensions preset `{name}`, expected `all`, `compat` or `none`"
+                ),
+                ExtensionsSetting::List(_) => format!("unknown parser extension `{name}`"),
+            };
+            for name in unknown {
+                problems.push((extensions.span(), DiagnosticSeverity::ERROR, message(name)));
+            }
+        }
+
+        let files = [&self.aisle_path, &self.pantry_path];
+        for path in files.into_iter().flatten() {
+            if !workspace_root.join(path.get_ref()).is_file() {
+                problems.push((
+                    path.span(),
+                    DiagnosticSeverity::WARNING,
+                    format!("file not found: {}", path.get_ref().display()),
+                ));
+            }
+        }
+        let directories = [&self.data_directory, &self.template_directory];
+        for path in directories.into_iter().flatten() {
+            if !workspace_root.join(path.get_ref()).is_dir() {
+                problems.push((
+                    path.span(),
+                    DiagnosticSeverity::WARNING,
+                    format!("directory not found: {}", path.get_ref().display()),
+                ));
+            }
+        }
+
+        problems
+    }
+}
+
+impl LintConfig {
+    fn read(table: Entries, problems: &mut Vec<Problem>) -> Self {
+        let mut config = Self::default();
+        for (key, node) in table {
+            let level = match key.get_ref().as_str() {
+                "metadata" => &mut config.metadata,
+                "references" => &mut config.references,
+                "portability" => &mut config.portability,
+                "max-step-sentences" => {
+                    config.max_step_sentences = read(node, problems).map(Spanned::into_inner);
+                    continue;
+                }
+                name if lints::is_rule(name) => {
+                    if let Some(level) = read(node, problems) {
+                        config.rules.insert(name.to_string(), level.into_inner());
+                    }
+                    continue;
+                }
+                _ => {
+                    unknown(&key, "lint rule", problems);
+                    continue;
+                }
+            };
+            *level = read(node, problems).map(Spanned::into_inner);
+        }
+        config
+    }
+}
+
+/// Read `.cooklang.toml` from the workspace root, if there is one
+pub fn load(workspace_root: &Path) -> Option<ProjectFile> {
+    let path = workspace_root.join(FILE_NAME);
+    let content = std::fs::read_to_string(&path).ok()?;
+    tracing::info!("Loading project configuration from {:?}", path);
+    let (config, diagnostics) = parse(&content, workspace_root);
+    Some(ProjectFile {
+        path,
+        config,
+        diagnostics,
+    })
+}
+
+/// The settings of a workspace outside an editor: the defaults, overridden
+/// by its `.cooklang.toml`, if there is one
+pub fn workspace_settings(workspace_root: &Path) -> (Settings, Option<ProjectFile>) {
+    let mut settings = Settings::default();
+    let project = load(workspace_root);
+    if let Some(ref project) = project {
+        project.config.apply(&mut settings);
+    }
+    (settings, project)
+}
+
+/// Parse a project configuration and check it against the workspace
+pub fn parse(content: &str, workspace_root: &Path) -> (ProjectConfig, Vec<Diagnostic>) {
+    let line_index = LineIndex::new(content);
+    let diagnostic = |span: Range<usize>, severity, message: String| Diagnostic {
+        range: span_to_range(span.start, span.end, &line_index),
+        severity: Some(severity),
+        source: Some("cooklang".into()),
+        message,
+        ..Default::default()
+    };
+
+    match toml::from_str::<Node>(content) {
+        Ok(Node::Table(table)) => {
+            let mut problems = Vec::new();
+            let config = ProjectConfig::read(table, &mut problems);
+            problems.extend(config.problems(workspace_root));
+            let diagnostics = problems
+                .into_iter()
+                .map(|(span, severity, message)| diagnostic(span, severity, message))
+                .collect();
+            (config, diagnostics)
+        }
+        // A document is always a table
+        Ok(Node::Value(_)) => (ProjectConfig::default(), Vec::new()),
+        Err(err) => {
+            tracing::warn!("Invalid {}: {}", FILE_NAME, err.message());
+            let error = diagnostic(
+                err.span().unwrap_or_default(),
+                DiagnosticSeverity::ERROR,
+                err.message().to_string(),
+            );
+            (ProjectConfig::default(), vec![error])
+        }
+    }
+}
+
+/// Whether `path` is the project configuration of `workspace_root`
+pub fn is_project_file(path: &Path, workspace_root: &Path) -> bool {
+    path == workspace_root.join(FILE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cooklang::Extensions;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn overrides_only_what_it_sets() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("shop")).unwrap();
+        fs::write(dir.path().join("shop/aisle.conf"), "").unwrap();
+        let content = r#"
+extensions = ["modes", "range_values"]
+aisle-path = "shop/aisle.conf"
+
+[lints]
+references = "warning"
+portability = false
+unused-cookware = "error"
+max-step-sentences = 8
+
+[format]
+section-style = "single"
+"#;
+        let (config, diagnostics) = parse(content, dir.path());
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+
+        let mut settings = Settings::from_json(serde_json::json!({
+            "pantryPath": "pantry.conf",
+            "aislePath": "aisle.conf",
+            "lints": { "metadata": false },
+        }));
+        config.apply(&mut settings);
+        assert_eq!(
+            settings.extensions(),
+            Extensions::MODES | Extensions::RANGE_VALUES
+        );
+        assert_eq!(settings.aisle_path, Some("shop/aisle.conf".into()));
+        assert_eq!(settings.pantry_path, Some("pantry.conf".into()));
+        assert_eq!(settings.lints.metadata, LintLevel::Off);
+        assert_eq!(settings.lints.references, LintLevel::Warning);
+        assert_eq!(settings.lints.portability, LintLevel::Off);
+        assert_eq!(settings.lints.rule("unused-cookware"), LintLevel::Error);
+        assert_eq!(settings.lints.max_step_sentences, 8);
+        assert!(settings.completion.common);
+        assert_eq!(settings.format.section_style, SectionStyle::Single);
+    }
+
+    #[test]
+    fn reports_problems_where_they_are() {
+        let dir = TempDir::new().unwrap();
+
+        let (_, diagnostics) = parse("[lints]\nmetadata = \"loud\"\n", dir.path());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start.line, 1);
+        assert!(diagnostics[0].message.contains("unknown lint level `loud`"));
+
+        let (_, diagnostics) = parse("aisle = \"aisle.conf\"\n", dir.path());
+        assert_eq!(diagnostics[0].message, "unknown key `aisle`");
+        assert_eq!(diagnostics[0].range.end.character, 5);
+
+        let (_, diagnostics) = parse("[lints]\n\nlong-steps = \"off\"\n", dir.path());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start.line, 2);
+        assert_eq!(diagnostics[0].message, "unknown lint rule `long-steps`");
+
+        let content = "extensions = [\"MODES\", \"TELEPORT\"]\npantry-path = \"pantry.conf\"\n";
+        let (_, diagnostics) = parse(content, dir.path());
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.range.start.line, d.severity, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    0,
+                    Some(DiagnosticSeverity::ERROR),
+                    "unknown parser extension `TELEPORT`"
+                ),
+                (
+                    1,
+                    Some(DiagnosticSeverity::WARNING),
+                    "file not found: pantry.conf"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn loads_from_workspace_root() {
+        let dir = TempDir::new().unwrap();
+        assert!(load(dir.path()).is_none());
+
+        fs::write(dir.path().join(FILE_NAME), "locale = \"fr\"\n").unwrap();
+        let file = load(dir.path()).unwrap();
+        assert!(is_project_file(&file.path, dir.path()));
+        let mut settings = Settings::default();
+        file.config.apply(&mut settings);
+        assert_eq!(settings.locale.as_deref(), Some("fr"));
+
+        // Keys that can't be read are skipped
+        fs::write(
+            dir.path().join(FILE_NAME),
+            "locale = \"fr\"\nlints = 3\nextensions = 1\n\n\
+             [format]\nsection-style = \"triple\"\ncompact-quantities = false\n\
+             [completion]\ncommon = false\nrecipes = true\n",
+        )
+        .unwrap();
+        let file = load(dir.path()).unwrap();
+        let problems: Vec<_> = file
+            .diagnostics
+            .iter()
+            .map(|d| (d.range.start.line, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                (1, "invalid type: integer, expected a table"),
+                (
+                    2,
+                    "data did not match any variant of untagged enum ExtensionsSetting"
+                ),
+                (
+                    5,
+                    "unknown variant `triple`, expected one of `keep`, `single`, `double`"
+                ),
+                (9, "unknown key `recipes`"),
+            ]
+        );
+        let mut settings = Settings::default();
+        file.config.apply(&mut settings);
+        assert_eq!(settings.locale.as_deref(), Some("fr"));
+        assert!(!settings.format.compact_quantities);
+        assert!(!settings.completion.common);
+
+        // Invalid TOML sets nothing
+        fs::write(dir.path().join(FILE_NAME), "locale = \n").unwrap();
+        let file = load(dir.path()).unwrap();
+        assert_eq!(file.diagnostics.len(), 1);
+        let mut settings = Settings::default();
+        file.config.apply(&mut settings);
+        assert_eq!(settings, Settings::default());
+    }
+}
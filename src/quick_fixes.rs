@@ -0,0 +1,494 @@
+//! Common mistakes the parser doesn't report, each with a quick fix.
+//!
+//! The parser is lenient: what doesn't parse as a component is read as text.
+//! `@salt{1%tsp and mix` is the ingredient "salt" followed by the text
+//! "{1%tsp and mix", and `@olive oil` is "olive" followed by " oil". These
+//! are found by looking at the text right after each component and at
+//! quantities read as text, and reported as diagnostics with a code. The
+//! code action for one replaces the mistake with the most likely fix.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use cooklang::parser::{Event, PullParser, Quantity};
+use cooklang::Located;
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, DiagnosticSeverity,
+    NumberOrString, TextEdit, WorkspaceEdit,
+};
+
+use crate::document::Document;
+use crate::i18n::{fill, Language, Strings};
+use crate::state::ServerState;
+use crate::utils::position::span_to_range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mistake {
+    /// `@salt{1%tsp`
+    UnclosedQuantity,
+    /// `@salt{}(fine`
+    UnclosedNote,
+    /// `@olive oil`
+    UnbracedName,
+    /// `@salt{1;tsp}`
+    QuantitySeparator,
+    /// `@salt{1,5%tsp}`
+    DecimalComma,
+}
+
+impl Mistake {
+    /// The diagnostic code
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::UnclosedQuantity => "unclosed-quantity",
+            Self::UnclosedNote => "unclosed-note",
+            Self::UnbracedName => "unbraced-name",
+            Self::QuantitySeparator => "quantity-separator",
+            Self::DecimalComma => "decimal-comma",
+        }
+    }
+}
+
+/// A mistake and its fix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuickFix {
+    pub mistake: Mistake,
+    /// The source of the mistake, replaced by the fix
+    pub span: Range<usize>,
+    pub replacement: String,
+    pub message: String,
+}
+
+impl QuickFix {
+    pub fn diagnostic(&self, doc: &Document) -> Diagnostic {
+        Diagnostic {
+            range: span_to_range(self.span.start, self.span.end, &doc.line_index),
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: Some(NumberOrString::String(self.mistake.code().into())),
+            source: Some("cooklang".into()),
+            message: self.message.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn code_action(&self, doc: &Document, strings: &Strings) -> CodeAction {
+        let diagnostic = self.diagnostic(doc);
+        let edit = TextEdit {
+            range: diagnostic.range,
+            new_text: self.replacement.clone(),
+        };
+        CodeAction {
+            title: fill(strings.change_to, &[&self.replacement]),
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diagnostic]),
+            edit: Some(WorkspaceEdit {
+                changes: Some(HashMap::from([(doc.uri.clone(), vec![edit])])),
+                ..Default::default()
+            }),
+            is_preferred: Some(true),
+            ..Default::default()
+        }
+    }
+}
+
+/// Quick fixes for the mistakes in `range` (byte offsets) of `doc`
+pub fn code_actions(
+    doc: &Document,
+    range: Range<usize>,
+    state: &ServerState,
+) -> Vec<CodeActionOrCommand> {
+    let strings = state.language_for(doc).strings();
+    find(doc, state, strings)
+        .into_iter()
+        .filter(|fix| fix.span.start <= range.end && range.start <= fix.span.end)
+        .map(|fix| CodeActionOrCommand::CodeAction(fix.code_action(doc, strings)))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Ingredient,
+    Cookware,
+    Timer,
+}
+
+/// The component just before a text event
+struct Previous {
+    kind: Kind,
+    span: Range<usize>,
+    name: String,
+}
+
+/// Every mistake in `doc`, in source order
+pub fn find(doc: &Document, state: &ServerState, strings: &Strings) -> Vec<QuickFix> {
+    let content = doc.content.as_str();
+    let mut fixes = Vec::new();
+    let mut previous = None;
+
+    for event in PullParser::new(content, doc.extensions) {
+        match event {
+            Event::Ingredient(ingredient) => {
+                quantity_mistakes(content, ingredient.quantity.as_ref(), strings, &mut fixes);
+                previous = Some(Previous {
+                    kind: Kind::Ingredient,
+                    span: ingredient.span().range(),
+                    name: ingredient.name.text_trimmed().into_owned(),
+                });
+            }
+            Event::Cookware(cookware) => {
+                quantity_mistakes(content, cookware.quantity.as_ref(), strings, &mut fixes);
+                previous = Some(Previous {
+                    kind: Kind::Cookware,
+                    span: cookware.span().range(),
+                    name: cookware.name.text_trimmed().into_owned(),
+                });
+            }
+            Event::Timer(timer) => {
+                quantity_mistakes(content, timer.quantity.as_ref(), strings, &mut fixes);
+                previous = Some(Previous {
+                    kind: Kind::Timer,
+                    span: timer.span().range(),
+                    name: String::new(),
+                });
+            }
+            Event::Text(text) => {
+                let span = text.span().range();
+                if let Some(component) = previous.take().filter(|c| c.span.end == span.start) {
+                    let language = state.language_for(doc);
+                    let known = |kind: Kind, name: &str| is_known(state, language, kind, name);
+                    if let Some(fix) = after_component(content, &component, strings, known) {
+                        fixes.push(fix);
+                    }
+                }
+                unnamed_timers(content, span, strings, &mut fixes);
+            }
+            _ => previous = None,
+        }
+    }
+
+    fixes.sort_by_key(|fix| fix.span.start);
+    fixes
+}
+
+/// Mistakes in the text right after a component
+fn after_component(
+    content: &str,
+    component: &Previous,
+    strings: &Strings,
+    known: impl Fn(Kind, &str) -> bool,
+) -> Option<QuickFix> {
+    let start = component.span.end;
+    let rest = &content[start..line_end(content, start)];
+    // A component ending in `}` or `)` got its braces
+    let braced = content[..start].ends_with(['}', ')']);
+    let fix = |mistake, end: usize, closing: &str, message: String| QuickFix {
+        mistake,
+        span: component.span.start..end,
+        replacement: format!("{}{}", &content[component.span.start..end], closing),
+        message,
+    };
+
+    // `@salt{1%tsp` or `@olive oil{1%tbsp`
+    let words = rest
+        .find(|c: char| !is_name_char(c) && c != ' ')
+        .unwrap_or(rest.len());
+    if !braced && rest[words..].starts_with('{') && !rest[words..].contains('}') {
+        let open = start + words;
+        let end = open + 1 + quantity_len(&rest[words + 1..]);
+        return Some(fix(
+            Mistake::UnclosedQuantity,
+            end,
+            "}",
+            strings.unclosed_quantity.into(),
+        ));
+    }
+
+    // `@salt{}(fine grain` or `@leek(sliced`
+    if rest.starts_with('(') && !rest.contains(')') {
+        let end = start + 1 + note_len(&rest[1..]);
+        return Some(fix(
+            Mistake::UnclosedNote,
+            end,
+            ")",
+            strings.unclosed_note.into(),
+        ));
+    }
+
+    // `@olive oil`: the longest name of up to three more words that is an
+    // ingredient or cookware the server knows
+    if braced || component.kind == Kind::Timer {
+        return None;
+    }
+    let mut end = 0;
+    let mut name_end = None;
+    for _ in 0..3 {
+        let Some(word) = rest[end..].strip_prefix(' ') else {
+            break;
+        };
+        let len = word.find(|c| !is_name_char(c)).unwrap_or(word.len());
+        if len == 0 {
+            break;
+        }
+        end += 1 + len;
+        if known(
+            component.kind,
+            &format!("{}{}", component.name, &rest[..end]),
+        ) {
+            name_end = Some(end);
+        }
+    }
+    let name_end = start + name_end?;
+    let read_as = content[component.span.clone()].trim_start_matches(['@', '#']);
+    Some(fix(
+        Mistake::UnbracedName,
+        name_end,
+        "{}",
+        fill(strings.unbraced_name, &[read_as, "{}"]),
+    ))
+}
+
+/// Unnamed timers missing their `}` (`~{10%min`), which are read as text
+fn unnamed_timers(content: &str, span: Range<usize>, strings: &Strings, fixes: &mut Vec<QuickFix>) {
+    let text = &content[span.clone()];
+    for (at, _) in text.match_indices("~{") {
+        let start = span.start + at;
+        let rest = &content[start + 2..line_end(content, start)];
+        if rest.contains('}') {
+            continue;
+        }
+        let end = start + 2 + quantity_len(rest);
+        fixes.push(QuickFix {
+            mistake: Mistake::UnclosedQuantity,
+            span: start..end,
+            replacement: format!("{}}}", &content[start..end]),
+            message: strings.unclosed_quantity.into(),
+        });
+    }
+}
+
+/// Quantities read as text because of the separator: `{1;tsp}`, `{1,5%g}`
+fn quantity_mistakes(
+    content: &str,
+    quantity: Option<&Located<Quantity>>,
+    strings: &Strings,
+    fixes: &mut Vec<QuickFix>,
+) {
+    let Some(quantity) = quantity else {
+        return;
+    };
+    if !quantity.value.value.is_text() {
+        return;
+    }
+    let span = quantity.value.span().range();
+    let raw = &content[span.clone()];
+    let amount_len = raw
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '/'))
+        .unwrap_or(raw.len());
+    let (amount, rest) = raw.split_at(amount_len);
+    if amount.is_empty() {
+        return;
+    }
+
+    // `1,5`
+    if let Some(decimals) = rest.strip_prefix(',') {
+        if !decimals.is_empty() && decimals.chars().all(|c| c.is_ascii_digit()) {
+            fixes.push(QuickFix {
+                mistake: Mistake::DecimalComma,
+                span,
+                replacement: raw.replacen(',', ".", 1),
+                message: fill(strings.decimal_comma, &[raw]),
+            });
+            return;
+        }
+    }
+
+    // `1;tsp`, `2 : cups`
+    if quantity.unit.is_some() {
+        return;
+    }
+    let Some(unit) = rest.trim_start().strip_prefix([';', ':', ',']) else {
+        return;
+    };
+    let unit = unit.trim_start();
+    if unit.starts_with(char::is_alphabetic) {
+        fixes.push(QuickFix {
+            mistake: Mistake::QuantitySeparator,
+            span,
+            replacement: format!("{amount}%{unit}"),
+            message: fill(strings.quantity_separator, &[raw]),
+        });
+    }
+}
+
+/// Length of a quantity missing its `}`: up to the end of the unit after a
+/// `%` (`1%tsp and mix`), or else of the first word (`2 and mix`)
+fn quantity_len(text: &str) -> usize {
+    let amount = text
+        .find(|c: char| !(c.is_ascii_digit() || " ./-=".contains(c)))
+        .unwrap_or(text.len());
+    if let Some(unit) = text[amount..].strip_prefix('%') {
+        let spaces = unit.len() - unit.trim_start().len();
+        let unit = &unit[spaces..];
+        let len = unit
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(unit.len());
+        return amount + 1 + spaces + len;
+    }
+    let word = text.find(char::is_whitespace).unwrap_or(text.len());
+    text[..word]
+        .trim_end_matches([',', '.', ';', ':', '!', '?'])
+        .len()
+}
+
+/// Length of a note missing its `)`: up to the end of the clause or line
+fn note_len(text: &str) -> usize {
+    let clause = text
+        .char_indices()
+        .find(|&(i, c)| {
+            matches!(c, ',' | ';' | '.')
+                && text[i + 1..].chars().next().is_none_or(char::is_whitespace)
+        })
+        .map_or(text.len(), |(i, _)| i);
+    text[..clause].trim_end().len()
+}
+
+fn line_end(content: &str, from: usize) -> usize {
+    content[from..]
+        .find('\n')
+        .map_or(content.len(), |i| from + i)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '\''
+}
+
+/// Whether `name` is an ingredient or cookware from the completion data,
+/// aisle.conf or the workspace
+fn is_known(state: &ServerState, language: Language, kind: Kind, name: &str) -> bool {
+    let name = name.to_lowercase();
+    let data = state.completion_data(language);
+    match kind {
+        Kind::Ingredient => {
+            data.ingredients.iter().any(|i| i.to_lowercase() == name)
+                || state
+                    .get_aisle_ingredients()
+                    .iter()
+                    .any(|i| i.name.to_lowercase() == name)
+                || state
+                    .index
+                    .iter()
+                    .any(|entry| entry.value().ingredients.iter().any(|u| u.name == name))
+        }
+        Kind::Cookware => data.cookware.iter().any(|c| c.to_lowercase() == name),
+        Kind::Timer => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::EN;
+    use tower_lsp::lsp_types::Url;
+
+    fn fixes(content: &str) -> Vec<(Mistake, String, String)> {
+        let state = ServerState::new();
+        let uri = Url::parse("file:///test.cook").unwrap();
+        state.open_document(uri.clone(), 1, content.into());
+        let doc = state.get_document(&uri).unwrap();
+        find(&doc, &state, &EN)
+            .into_iter()
+            .map(|fix| {
+                let before = content[fix.span].to_string();
+                (fix.mistake, before, fix.replacement)
+            })
+            .collect()
+    }
+
+    fn fix(mistake: Mistake, before: &str, after: &str) -> (Mistake, String, String) {
+        (mistake, before.into(), after.into())
+    }
+
+    #[test]
+    fn unclosed_quantities() {
+        assert_eq!(
+            fixes("Add @salt{1%tsp and mix.\nHeat #pot{\nWait ~{10 % min.\nRest ~dough{2"),
+            vec![
+                fix(Mistake::UnclosedQuantity, "@salt{1%tsp", "@salt{1%tsp}"),
+                fix(Mistake::UnclosedQuantity, "#pot{", "#pot{}"),
+                fix(Mistake::UnclosedQuantity, "~{10 % min", "~{10 % min}"),
+                fix(Mistake::UnclosedQuantity, "~dough{2", "~dough{2}"),
+            ]
+        );
+        assert_eq!(
+            fixes("Add @olive oil{2%tbsp, then stir."),
+            vec![fix(
+                Mistake::UnclosedQuantity,
+                "@olive oil{2%tbsp",
+                "@olive oil{2%tbsp}"
+            )]
+        );
+        assert!(fixes("Add @salt{1%tsp} and {brand} sauce.").is_empty());
+    }
+
+    #[test]
+    fn unclosed_notes() {
+        assert_eq!(
+            fixes("Add @salt{}(fine grain, then @leek(sliced\nMix."),
+            vec![
+                fix(
+                    Mistake::UnclosedNote,
+                    "@salt{}(fine grain",
+                    "@salt{}(fine grain)"
+                ),
+                fix(Mistake::UnclosedNote, "@leek(sliced", "@leek(sliced)"),
+            ]
+        );
+        assert!(fixes("Add @salt (to taste.").is_empty());
+    }
+
+    #[test]
+    fn multi_word_names() {
+        assert_eq!(
+            fixes("Add @olive oil and @salt and pepper, then heat the #frying pan."),
+            vec![
+                fix(Mistake::UnbracedName, "@olive oil", "@olive oil{}"),
+                fix(Mistake::UnbracedName, "#frying pan", "#frying pan{}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn quantity_separators() {
+        assert_eq!(
+            fixes("Add @salt{1;tsp}, @milk{2 : cups} and @sugar{1,5%g}. Use @egg{2}."),
+            vec![
+                fix(Mistake::QuantitySeparator, "1;tsp", "1%tsp"),
+                fix(Mistake::QuantitySeparator, "2 : cups", "2%cups"),
+                fix(Mistake::DecimalComma, "1,5", "1.5"),
+            ]
+        );
+    }
+
+    #[test]
+    fn code_actions_in_range() {
+        let state = ServerState::new();
+        let uri = Url::parse("file:///test.cook").unwrap();
+        let content = "Add @salt{1%tsp and mix.\n\nAdd @sugar{1;tsp}.";
+        state.open_document(uri.clone(), 1, content.into());
+        let doc = state.get_document(&uri).unwrap();
+
+        let actions = code_actions(&doc, 38..38, &state);
+        assert_eq!(actions.len(), 1);
+        let CodeActionOrCommand::CodeAction(ref action) = actions[0] else {
+            panic!("expected a code action");
+        };
+        assert_eq!(action.title, "Change to `1%tsp`");
+        let diagnostics = action.diagnostics.as_ref().unwrap();
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("quantity-separator".into()))
+        );
+        let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+        assert_eq!(edits[0].new_text, "1%tsp");
+    }
+}
@@ -0,0 +1,189 @@
+//! Resolving recipe references (`@./sauces/pesto{2%servings}`) to files.
+//!
+//! Reference paths are written relative to the workspace root (that is what
+//! recipe reference completion inserts), but a path relative to the
+//! referencing recipe's own directory is accepted too.
+
+use std::path::{Path, PathBuf};
+
+use cooklang::model::{Ingredient, RecipeReference};
+use cooklang::quantity::Value;
+use cooklang::{Recipe, Span};
+use tower_lsp::lsp_types::Url;
+
+use crate::document::{parse_recipe, parser, Document};
+use crate::state::ServerState;
+use crate::utils::components::{scan_components, ComponentKind};
+
+/// Extensions a referenced file may have, in lookup order.
+const REFERENCE_EXTENSIONS: &[&str] = &["cook", "menu"];
+
+/// Find the file a reference points to.
+///
+/// `doc_dir` is the directory of the recipe containing the reference and
+/// `workspace_root` the workspace it belongs to; either may be unknown.
+pub fn resolve(
+    reference: &RecipeReference,
+    doc_dir: Option<&Path>,
+    workspace_root: Option<&Path>,
+) -> Option<PathBuf> {
+    let relative = reference.path("/");
+
+    for base in [doc_dir, workspace_root].into_iter().flatten() {
+        for ext in REFERENCE_EXTENSIONS {
+            let candidate = base.join(format!("{}.{}", relative, ext));
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+    }
+
+    None
+}
+
+/// Every recipe reference in `doc`, with the span of the component that
+/// wrote it and the parsed ingredient (which carries the path and quantity).
+pub fn located_references(doc: &Document) -> Vec<(Span, &Ingredient)> {
+    let Some(ref result) = doc.parse_result else {
+        return Vec::new();
+    };
+    let ingredients = &result.recipe.ingredients;
+
+    let mut found = Vec::new();
+    let mut seen: Vec<String> = Vec::new();
+    for component in scan_components(&doc.content) {
+        if component.kind != ComponentKind::Ingredient {
+            continue;
+        }
+        let path = component.name.replace('\\', "/");
+        if !path.starts_with("./") && !path.starts_with("../") {
+            continue;
+        }
+
+        // The same recipe can be referenced more than once (with different
+        // quantities); pair the n-th occurrence in the source with the n-th
+        // parsed ingredient for that path.
+        let occurrence = seen.iter().filter(|p| **p == path).count();
+        let ingredient = ingredients
+            .iter()
+            .filter(|i| i.reference.as_ref().is_some_and(|r| r.path("/") == path))
+            .nth(occurrence);
+        seen.push(path);
+
+        if let Some(ingredient) = ingredient {
+            found.push((component.span, ingredient));
+        }
+    }
+    found
+}
+
+/// The directory containing the document at `uri`, if it is a file.
+pub fn document_dir(uri: &Url) -> Option<PathBuf> {
+    uri.to_file_path()
+        .ok()
+        .and_then(|p| p.parent().map(Path::to_path_buf))
+}
+
+/// Load and parse the recipe at `path`, preferring the open editor buffer
+/// over the file on disk.
+pub fn load_recipe(path: &Path, state: &ServerState) -> Option<Recipe> {
+    let uri = Url::from_file_path(path).ok()?;
+    let content = state.read_source(&uri)?;
+    parse_recipe(&content)
+}
+
+/// Scale a referenced recipe by the quantity written on the reference:
+/// `{2%servings}` scales to 2 servings, a bare `{2}` is a factor, and no
+/// quantity leaves the recipe as written.
+pub fn scale_for_reference(recipe: &mut Recipe, reference: &Ingredient) {
+    let Some(quantity) = &reference.quantity else {
+        return;
+    };
+    let Value::Number(number) = quantity.value() else {
+        return;
+    };
+
+    let parser = parser();
+    if let Err(err) = recipe.scale_to_target(number.value(), quantity.unit(), parser.converter()) {
+        tracing::warn!(
+            "Could not scale referenced recipe {}: {}",
+            reference.name,
+            err
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn reference(src: &str) -> Ingredient {
+        parse_recipe(src)
+            .unwrap()
+            .ingredients
+            .into_iter()
+            .next()
+            .unwrap()
+    }
+
+    #[test]
+    fn resolves_relative_to_workspace_root() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("sauces")).unwrap();
+        fs::write(root.join("sauces/Pesto.cook"), "").unwrap();
+        fs::write(root.join("Week.menu"), "").unwrap();
+
+        let pesto = reference("@./sauces/Pesto{}");
+        let found = resolve(pesto.reference.as_ref().unwrap(), None, Some(root));
+        assert_eq!(found, Some(root.join("sauces/Pesto.cook")));
+
+        let week = reference("@./Week{}");
+        let found = resolve(week.reference.as_ref().unwrap(), None, Some(root));
+        assert_eq!(found, Some(root.join("Week.menu")));
+
+        let missing = reference("@./Nope{}");
+        assert!(resolve(missing.reference.as_ref().unwrap(), None, Some(root)).is_none());
+    }
+
+    #[test]
+    fn locates_repeated_references() {
+        let content = "@./Pesto{1%servings} then @salt and @./Pesto{3%servings}";
+        let doc = Document::new(
+            Url::parse("file:///week.menu").unwrap(),
+            1,
+            content.to_string(),
+        );
+        let refs = located_references(&doc);
+        assert_eq!(refs.len(), 2);
+        assert_eq!(
+            &content[refs[0].0.start()..refs[0].0.end()],
+            "@./Pesto{1%servings}"
+        );
+        assert_eq!(
+            refs[1].1.quantity.as_ref().unwrap().to_string(),
+            "3 servings"
+        );
+    }
+
+    #[test]
+    fn scales_by_servings_or_factor() {
+        let base = ">> servings: 2\n\nMix @basil{10%g}.";
+
+        let mut recipe = parse_recipe(base).unwrap();
+        scale_for_reference(&mut recipe, &reference("@./Pesto{4%servings}"));
+        assert_eq!(
+            recipe.ingredients[0].quantity.as_ref().unwrap().to_string(),
+            "20 g"
+        );
+
+        let mut recipe = parse_recipe(base).unwrap();
+        scale_for_reference(&mut recipe, &reference("@./Pesto{3}"));
+        assert_eq!(
+            recipe.ingredients[0].quantity.as_ref().unwrap().to_string(),
+            "30 g"
+        );
+    }
+}
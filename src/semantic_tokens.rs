@@ -1,26 +1,31 @@
 use tower_lsp::lsp_types::{
-    SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokensFullOptions,
-    SemanticTokensLegend, SemanticTokensOptions, SemanticTokensServerCapabilities,
+    Range, SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens,
+    SemanticTokensDelta, SemanticTokensEdit, SemanticTokensFullDeltaResult,
+    SemanticTokensFullOptions, SemanticTokensLegend, SemanticTokensOptions,
+    SemanticTokensServerCapabilities, Url,
 };
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use dashmap::DashMap;
 
 use crate::document::Document;
-use crate::utils::components::{scan_components, ComponentKind};
+use crate::metadata::front_matter_range;
+use crate::utils::components::{scan_components, Component, ComponentKind, ComponentPart};
+use crate::utils::line_index::LineIndex;
 
 // Token type indices
 const TOKEN_INGREDIENT: u32 = 0;
 const TOKEN_COOKWARE: u32 = 1;
 const TOKEN_TIMER: u32 = 2;
-#[allow(dead_code)]
-const TOKEN_QUANTITY: u32 = 3; // Reserved for future use
-#[allow(dead_code)]
-const TOKEN_UNIT: u32 = 4; // Reserved for future use
+const TOKEN_QUANTITY: u32 = 3;
+const TOKEN_UNIT: u32 = 4;
 const TOKEN_COMMENT: u32 = 5;
 const TOKEN_METADATA_KEY: u32 = 6;
-#[allow(dead_code)]
-const TOKEN_METADATA_VALUE: u32 = 7; // Reserved for future use
+const TOKEN_METADATA_VALUE: u32 = 7;
 const TOKEN_SECTION: u32 = 8;
+const TOKEN_MARKER: u32 = 9;
 
 pub const TOKEN_TYPES: &[SemanticTokenType] = &[
     SemanticTokenType::VARIABLE,  // 0: Ingredients (@)
@@ -32,9 +37,23 @@ pub const TOKEN_TYPES: &[SemanticTokenType] = &[
     SemanticTokenType::KEYWORD,   // 6: Metadata keys
     SemanticTokenType::PROPERTY,  // 7: Metadata values
     SemanticTokenType::NAMESPACE, // 8: Sections
+    SemanticTokenType::OPERATOR,  // 9: Component markers and modifiers (@?, #, ~)
 ];
 
-pub const TOKEN_MODIFIERS: &[SemanticTokenModifier] = &[];
+// Token modifier bits
+const MODIFIER_REFERENCE: u32 = 1 << 0;
+const MODIFIER_FIXED: u32 = 1 << 1;
+const MODIFIER_OPTIONAL: u32 = 1 << 2;
+const MODIFIER_HIDDEN: u32 = 1 << 3;
+const MODIFIER_UNDEFINED: u32 = 1 << 4;
+
+pub const TOKEN_MODIFIERS: &[SemanticTokenModifier] = &[
+    SemanticTokenModifier::new("reference"), // 0: `@&name` and recipe references (`@./recipe`)
+    SemanticTokenModifier::new("fixed"),     // 1: Quantities that don't scale (`{=2%cup}`)
+    SemanticTokenModifier::new("optional"),  // 2: `@?name`
+    SemanticTokenModifier::new("hidden"),    // 3: `@-name`
+    SemanticTokenModifier::new("undefined"), // 4: `@&name` with no earlier definition
+];
 
 pub fn legend() -> SemanticTokensLegend {
     SemanticTokensLegend {
@@ -46,12 +65,161 @@ pub fn legend() -> SemanticTokensLegend {
 pub fn capabilities() -> SemanticTokensServerCapabilities {
     SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
         legend: legend(),
-        full: Some(SemanticTokensFullOptions::Bool(true)),
-        range: Some(false),
+        full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
+        range: Some(true),
         work_done_progress_options: Default::default(),
     })
 }
 
+/// The last tokens sent for a document, kept to answer delta requests and
+/// to avoid recomputing tokens for an unchanged version.
+#[derive(Debug, Clone)]
+struct CachedTokens {
+    result_id: String,
+    version: i32,
+    data: Vec<SemanticToken>,
+}
+
+/// Semantic token results per document.
+#[derive(Debug, Default)]
+pub struct TokenCache {
+    entries: DashMap<Url, CachedTokens>,
+    next_id: AtomicU64,
+}
+
+impl TokenCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn remove(&self, uri: &Url) {
+        self.entries.remove(uri);
+    }
+
+    /// Tokens for the document's current version, from the cache if they
+    /// were already computed.
+    fn tokens(&self, doc: &Document) -> Vec<SemanticToken> {
+        match self.entries.get(&doc.uri) {
+            Some(cached) if cached.version == doc.version => cached.data.clone(),
+            _ => get_semantic_tokens(doc),
+        }
+    }
+
+    /// Store `data` as the latest result for `doc` and return its id.
+    fn store(&self, doc: &Document, data: Vec<SemanticToken>) -> String {
+        let result_id = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+        self.entries.insert(
+            doc.uri.clone(),
+            CachedTokens {
+                result_id: result_id.clone(),
+                version: doc.version,
+                data,
+            },
+        );
+        result_id
+    }
+
+    /// `textDocument/semanticTokens/full`
+    pub fn full(&self, doc: &Document) -> SemanticTokens {
+        let data = self.tokens(doc);
+        let result_id = self.store(doc, data.clone());
+        SemanticTokens {
+            result_id: Some(result_id),
+            data,
+        }
+    }
+
+    /// `textDocument/semanticTokens/full/delta`: edits against the result the
+    /// client has, or all tokens if that result is no longer cached.
+    pub fn full_delta(
+        &self,
+        doc: &Document,
+        previous_result_id: &str,
+    ) -> SemanticTokensFullDeltaResult {
+        let previous = self
+            .entries
+            .get(&doc.uri)
+            .filter(|cached| cached.result_id == previous_result_id)
+            .map(|cached| cached.data.clone());
+        let Some(previous) = previous else {
+            return SemanticTokensFullDeltaResult::Tokens(self.full(doc));
+        };
+
+        let data = self.tokens(doc);
+        let edits = token_edits(&previous, &data);
+        let result_id = self.store(doc, data);
+        SemanticTokensFullDeltaResult::TokensDelta(SemanticTokensDelta {
+            result_id: Some(result_id),
+            edits,
+        })
+    }
+
+    /// `textDocument/semanticTokens/range`
+    pub fn range(&self, doc: &Document, range: Range) -> SemanticTokens {
+        SemanticTokens {
+            result_id: None,
+            data: tokens_in_range(&self.tokens(doc), range),
+        }
+    }
+}
+
+/// The edit turning `old` into `new`: everything between their common
+/// prefix and suffix is replaced. Offsets count integers in the encoded
+/// array (five per token).
+fn token_edits(old: &[SemanticToken], new: &[SemanticToken]) -> Vec<SemanticTokensEdit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let deleted = old.len() - prefix - suffix;
+    let inserted = &new[prefix..new.len() - suffix];
+    if deleted == 0 && inserted.is_empty() {
+        return Vec::new();
+    }
+
+    vec![SemanticTokensEdit {
+        start: (prefix * 5) as u32,
+        delete_count: (deleted * 5) as u32,
+        data: Some(inserted.to_vec()),
+    }]
+}
+
+/// The tokens overlapping `range` (even if they start before it or end after
+/// it), re-encoded relative to each other.
+fn tokens_in_range(tokens: &[SemanticToken], range: Range) -> Vec<SemanticToken> {
+    let mut builder = TokenBuilder::new();
+    let (mut line, mut start) = (0, 0);
+
+    for token in tokens {
+        if token.delta_line > 0 {
+            line += token.delta_line;
+            start = token.delta_start;
+        } else {
+            start += token.delta_start;
+        }
+
+        // Tokens don't span lines
+        let ends_after_start =
+            (line, start + token.length) > (range.start.line, range.start.character);
+        let starts_before_end = (line, start) < (range.end.line, range.end.character);
+        if ends_after_start && starts_before_end {
+            builder.push(
+                line,
+                start,
+                token.length,
+                token.token_type,
+                token.token_modifiers_bitset,
+            );
+        }
+    }
+
+    builder.build()
+}
+
 struct TokenBuilder {
     tokens: Vec<SemanticToken>,
     prev_line: u32,
@@ -67,7 +235,7 @@ impl TokenBuilder {
         }
     }
 
-    fn push(&mut self, line: u32, start: u32, length: u32, token_type: u32) {
+    fn push(&mut self, line: u32, start: u32, length: u32, token_type: u32, modifiers: u32) {
         if length == 0 {
             return;
         }
@@ -84,13 +252,60 @@ impl TokenBuilder {
             delta_start,
             length,
             token_type,
-            token_modifiers_bitset: 0,
+            token_modifiers_bitset: modifiers,
         });
 
         self.prev_line = line;
         self.prev_start = start;
     }
 
+    /// Push a token for the byte range `start..end`, which must not span lines
+    fn push_span(
+        &mut self,
+        line_index: &LineIndex,
+        start: usize,
+        end: usize,
+        token_type: u32,
+        modifiers: u32,
+    ) {
+        let (line, col) = line_index.line_col(start as u32);
+        let length = line_index.utf16_len(start, end);
+        self.push(line, col, length, token_type, modifiers);
+    }
+
+    /// Push `key: value` tokens for a metadata line (without the `>>`, or a
+    /// front matter line) starting at byte `offset`. A YAML list item
+    /// (`- value`) is a value on its own.
+    fn push_metadata(&mut self, line_index: &LineIndex, offset: usize, line: &str) {
+        let indent = line.len() - line.trim_start().len();
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) if !line.trim_start().starts_with('-') => (key, Some(value)),
+            _ => ("", line.trim_start().strip_prefix('-')),
+        };
+
+        let key_end = offset + key.trim_end().len();
+        self.push_span(
+            line_index,
+            offset + indent,
+            key_end.max(offset + indent),
+            TOKEN_METADATA_KEY,
+            0,
+        );
+
+        if let Some(value) = value {
+            let value_start = offset + line.len() - value.len();
+            let leading = value.len() - value.trim_start().len();
+            let start = value_start + leading;
+            self.push_span(
+                line_index,
+                start,
+                start + value.trim().len(),
+                TOKEN_METADATA_VALUE,
+                0,
+            );
+        }
+    }
+
     fn build(self) -> Vec<SemanticToken> {
         self.tokens
     }
@@ -107,6 +322,43 @@ fn advance_to(chars: &mut std::iter::Peekable<std::str::CharIndices>, end: usize
     }
 }
 
+/// Modifier bits for the marker and name of each component. A `&` reference
+/// is `undefined` when no earlier component of the same kind has its name.
+fn component_modifiers(components: &[Component]) -> Vec<u32> {
+    components
+        .iter()
+        .enumerate()
+        .map(|(i, component)| {
+            let mods = component.modifiers;
+            let mut bits = 0;
+            if mods.is_reference() {
+                bits |= MODIFIER_REFERENCE;
+                let defined = components[..i].iter().any(|earlier| {
+                    earlier.kind == component.kind
+                        && !earlier.modifiers.is_reference()
+                        && earlier.name.eq_ignore_ascii_case(&component.name)
+                });
+                if !defined {
+                    bits |= MODIFIER_UNDEFINED;
+                }
+            }
+            if mods.is_recipe()
+                || component.name.starts_with("./")
+                || component.name.starts_with("../")
+            {
+                bits |= MODIFIER_REFERENCE;
+            }
+            if mods.is_optional() {
+                bits |= MODIFIER_OPTIONAL;
+            }
+            if mods.is_hidden() {
+                bits |= MODIFIER_HIDDEN;
+            }
+            bits
+        })
+        .collect()
+}
+
 pub fn get_semantic_tokens(doc: &Document) -> Vec<SemanticToken> {
     let mut builder = TokenBuilder::new();
     let content = &doc.content;
@@ -114,30 +366,68 @@ pub fn get_semantic_tokens(doc: &Document) -> Vec<SemanticToken> {
 
     // Components (ingredients, cookware, timers) come straight from the parser
     // so their spans match how the recipe parses, keyed by their start offset.
-    let components: HashMap<usize, _> = scan_components(content)
-        .into_iter()
-        .map(|c| (c.span.start(), c))
+    let components = scan_components(content);
+    let modifiers = component_modifiers(&components);
+    let components: HashMap<usize, _> = components
+        .iter()
+        .zip(modifiers)
+        .map(|(c, m)| (c.span.start(), (c, m)))
         .collect();
 
+    let front_matter = front_matter_range(content).filter(|range| !range.is_empty());
+
     // Scan through the document and identify tokens
     let mut chars = content.char_indices().peekable();
 
     while let Some((idx, ch)) = chars.next() {
+        // YAML front matter body: one `key: value` (or `- value`) per line
+        if let Some(ref range) = front_matter {
+            if idx == range.start {
+                let mut offset = range.start;
+                for line in content[range.clone()].split_inclusive('\n') {
+                    let text = line.trim_end_matches(['\n', '\r']);
+                    if !text.trim_start().starts_with('#') {
+                        builder.push_metadata(line_index, offset, text);
+                    }
+                    offset += line.len();
+                }
+                advance_to(&mut chars, range.end);
+                continue;
+            }
+        }
+
         match ch {
-            // Ingredient (@), cookware (#) and timer (~) — use the parser span.
+            // Ingredient (@), cookware (#) and timer (~) — use the parser's
+            // spans for each part (marker, name, quantity, unit, note).
             '@' | '#' | '~' => {
-                if let Some(component) = components.get(&idx) {
-                    let end = component.span.end();
-                    advance_to(&mut chars, end);
+                if let Some(&(component, modifiers)) = components.get(&idx) {
+                    advance_to(&mut chars, component.span.end());
 
-                    let token_type = match component.kind {
+                    let name_type = match component.kind {
                         ComponentKind::Ingredient => TOKEN_INGREDIENT,
                         ComponentKind::Cookware => TOKEN_COOKWARE,
                         ComponentKind::Timer => TOKEN_TIMER,
                     };
-                    let (line, col) = line_index.line_col(idx as u32);
-                    let length = line_index.utf16_len(idx, end);
-                    builder.push(line, col, length, token_type);
+                    for (span, part) in &component.parts {
+                        let (token_type, token_modifiers) = match part {
+                            ComponentPart::Marker => (TOKEN_MARKER, modifiers),
+                            ComponentPart::Name => (name_type, modifiers),
+                            ComponentPart::Quantity if content[span.range()].starts_with('=') => {
+                                (TOKEN_QUANTITY, MODIFIER_FIXED)
+                            }
+                            ComponentPart::Quantity => (TOKEN_QUANTITY, 0),
+                            ComponentPart::Unit => (TOKEN_UNIT, 0),
+                            // Notes read like inline comments
+                            ComponentPart::Note => (TOKEN_COMMENT, 0),
+                        };
+                        builder.push_span(
+                            line_index,
+                            span.start(),
+                            span.end(),
+                            token_type,
+                            token_modifiers,
+                        );
+                    }
                 }
                 // Otherwise it's a stray marker (e.g. inside a block comment or
                 // a modifier); leave it untokenized.
@@ -175,7 +465,7 @@ pub fn get_semantic_tokens(doc: &Document) -> Vec<SemanticToken> {
                                 // Highlight the --- line as metadata
                                 let (line, col) = line_index.line_col(start as u32);
                                 let length = line_index.utf16_len(start, end);
-                                builder.push(line, col, length, TOKEN_METADATA_KEY);
+                                builder.push(line, col, length, TOKEN_METADATA_KEY, 0);
                                 continue;
                             }
                         }
@@ -193,7 +483,7 @@ pub fn get_semantic_tokens(doc: &Document) -> Vec<SemanticToken> {
 
                     let (line, col) = line_index.line_col(start as u32);
                     let length = line_index.utf16_len(start, end);
-                    builder.push(line, col, length, TOKEN_COMMENT);
+                    builder.push(line, col, length, TOKEN_COMMENT, 0);
                 }
             }
 
@@ -223,7 +513,7 @@ pub fn get_semantic_tokens(doc: &Document) -> Vec<SemanticToken> {
                     if found_closing {
                         let (line, col) = line_index.line_col(start as u32);
                         let length = line_index.utf16_len(start, end);
-                        builder.push(line, col, length, TOKEN_SECTION);
+                        builder.push(line, col, length, TOKEN_SECTION, 0);
                     }
                 }
             }
@@ -231,22 +521,15 @@ pub fn get_semantic_tokens(doc: &Document) -> Vec<SemanticToken> {
             // Metadata: >> key: value
             '>' => {
                 if let Some(&(_, '>')) = chars.peek() {
-                    let start = idx;
                     chars.next();
-                    let mut end = idx + 2;
-
-                    // Read until end of line
-                    while let Some(&(i, c)) = chars.peek() {
-                        if c == '\n' {
-                            break;
-                        }
-                        end = i + c.len_utf8();
-                        chars.next();
-                    }
-
-                    let (line, col) = line_index.line_col(start as u32);
-                    let length = line_index.utf16_len(start, end);
-                    builder.push(line, col, length, TOKEN_METADATA_KEY);
+                    let rest_start = idx + 2;
+                    let rest_end = content[rest_start..]
+                        .find(['\n', '\r'])
+                        .map_or(content.len(), |i| rest_start + i);
+                    advance_to(&mut chars, rest_end);
+
+                    builder.push_span(line_index, idx, rest_start, TOKEN_METADATA_KEY, 0);
+                    builder.push_metadata(line_index, rest_start, &content[rest_start..rest_end]);
                 }
             }
 
@@ -261,7 +544,6 @@ pub fn get_semantic_tokens(doc: &Document) -> Vec<SemanticToken> {
 mod tests {
     use super::*;
     use crate::document::Document;
-    use tower_lsp::lsp_types::Url;
 
     fn tokens(content: &str) -> Vec<SemanticToken> {
         let doc = Document::new(
@@ -272,26 +554,177 @@ mod tests {
         get_semantic_tokens(&doc)
     }
 
+    /// (type, absolute column, length) of each token on a single-line input
+    fn flat(toks: &[SemanticToken]) -> Vec<(u32, u32, u32)> {
+        let mut col = 0;
+        toks.iter()
+            .map(|t| {
+                col += t.delta_start;
+                (t.token_type, col, t.length)
+            })
+            .collect()
+    }
+
     #[test]
     fn multi_word_ingredient_highlighted_as_one_token() {
-        // Regression for cooklang/CookVSCode#10: the highlight must span the
-        // whole `@heavy whipping cream{1%cup}`, not stop at `@heavy`.
+        // Regression for cooklang/CookVSCode#10: the name must span the
+        // whole `heavy whipping cream`, not stop at `heavy`.
         let toks = tokens("Chill @heavy whipping cream{1%cup}.");
-        assert_eq!(toks.len(), 1);
-        let t = &toks[0];
-        assert_eq!(t.token_type, TOKEN_INGREDIENT);
-        assert_eq!(t.delta_start, 6); // column of '@'
-        assert_eq!(t.length, "@heavy whipping cream{1%cup}".len() as u32);
+        assert_eq!(
+            flat(&toks),
+            vec![
+                (TOKEN_MARKER, 6, 1),
+                (TOKEN_INGREDIENT, 7, "heavy whipping cream".len() as u32),
+                (TOKEN_QUANTITY, 28, 1),
+                (TOKEN_UNIT, 30, 3),
+            ]
+        );
     }
 
     #[test]
     fn lookahead_stops_at_next_marker() {
         // `@multi` is single-word; the `{}` belongs to `#tool`.
         let toks = tokens("@multi word #tool{} end.");
-        assert_eq!(toks.len(), 2);
-        assert_eq!(toks[0].token_type, TOKEN_INGREDIENT);
-        assert_eq!(toks[0].length, "@multi".len() as u32);
-        assert_eq!(toks[1].token_type, TOKEN_COOKWARE);
-        assert_eq!(toks[1].length, "#tool{}".len() as u32);
+        assert_eq!(
+            flat(&toks),
+            vec![
+                (TOKEN_MARKER, 0, 1),
+                (TOKEN_INGREDIENT, 1, 5),
+                (TOKEN_MARKER, 12, 1),
+                (TOKEN_COOKWARE, 13, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn component_note_and_modifiers() {
+        let toks = tokens("@?salt{=2%tsp}(fine)");
+        assert_eq!(
+            flat(&toks),
+            vec![
+                (TOKEN_MARKER, 0, 2),
+                (TOKEN_INGREDIENT, 2, 4),
+                (TOKEN_QUANTITY, 7, 2),
+                (TOKEN_UNIT, 10, 3),
+                (TOKEN_COMMENT, 15, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn component_modifiers_from_parser() {
+        let toks = tokens("@?salt{=1} @-oil{} @./sauces/Pesto{} @&salt @&pepper");
+        let names: Vec<_> = toks
+            .iter()
+            .filter(|t| t.token_type == TOKEN_INGREDIENT)
+            .map(|t| t.token_modifiers_bitset)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                MODIFIER_OPTIONAL,
+                MODIFIER_HIDDEN,
+                MODIFIER_REFERENCE,
+                MODIFIER_REFERENCE,
+                MODIFIER_REFERENCE | MODIFIER_UNDEFINED,
+            ]
+        );
+
+        let fixed = toks
+            .iter()
+            .find(|t| t.token_type == TOKEN_QUANTITY)
+            .unwrap();
+        assert_eq!(fixed.token_modifiers_bitset, MODIFIER_FIXED);
+    }
+
+    #[test]
+    fn range_keeps_tokens_inside_and_reencodes() {
+        let toks = tokens("@a{}\n#b{}\n~c{1%min}");
+        let range = Range {
+            start: tower_lsp::lsp_types::Position::new(1, 0),
+            end: tower_lsp::lsp_types::Position::new(2, 0),
+        };
+        let ranged = tokens_in_range(&toks, range);
+        // `#b`: marker and name, the first one relative to the document start
+        assert_eq!(ranged.len(), 2);
+        assert_eq!((ranged[0].delta_line, ranged[0].delta_start), (1, 0));
+        assert_eq!(ranged[1].token_type, TOKEN_COOKWARE);
+
+        // Inside the name of `#bowl`
+        let toks = tokens("@a{}\n#bowl{}");
+        let range = Range {
+            start: tower_lsp::lsp_types::Position::new(1, 2),
+            end: tower_lsp::lsp_types::Position::new(1, 3),
+        };
+        let ranged = tokens_in_range(&toks, range);
+        assert_eq!(ranged.len(), 1);
+        assert_eq!((ranged[0].delta_line, ranged[0].delta_start), (1, 1));
+        assert_eq!(ranged[0].length, 4);
+    }
+
+    #[test]
+    fn delta_replaces_changed_tokens_only() {
+        let old = tokens("@a{} #b{} ~c{1%min}");
+        let new = tokens("@a{} #bowl{} ~c{1%min}");
+        let edits = token_edits(&old, &new);
+        assert_eq!(edits.len(), 1);
+        // Marker and name of `@a` and the `#` marker are unchanged
+        assert_eq!(edits[0].start, 3 * 5);
+
+        let mut applied = old.clone();
+        let edit = &edits[0];
+        let start = edit.start as usize / 5;
+        let end = start + edit.delete_count as usize / 5;
+        applied.splice(start..end, edit.data.clone().unwrap());
+        assert_eq!(applied, new);
+
+        assert!(token_edits(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn cache_answers_delta_for_known_result() {
+        let cache = TokenCache::new();
+        let uri = Url::parse("file:///test.cook").unwrap();
+        let mut doc = Document::new(uri, 1, "@a{} #b{}".to_string());
+
+        let first = cache.full(&doc);
+        doc.update(2, "@a{} #bowl{}".to_string());
+
+        let delta = cache.full_delta(&doc, first.result_id.as_deref().unwrap());
+        let SemanticTokensFullDeltaResult::TokensDelta(delta) = delta else {
+            panic!("expected a delta");
+        };
+        assert_eq!(delta.edits.len(), 1);
+
+        // An unknown result id falls back to all tokens
+        let full = cache.full_delta(&doc, "stale");
+        assert!(matches!(full, SemanticTokensFullDeltaResult::Tokens(_)));
+    }
+
+    #[test]
+    fn metadata_keys_and_values() {
+        let toks = tokens(">> prep time: 10 min");
+        assert_eq!(
+            flat(&toks),
+            vec![
+                (TOKEN_METADATA_KEY, 0, 2),
+                (TOKEN_METADATA_KEY, 3, 9),
+                (TOKEN_METADATA_VALUE, 14, 6),
+            ]
+        );
+
+        let toks = tokens("---\ntitle: Soup\ntags:\n  - quick\n---\nBoil.");
+        let types: Vec<_> = toks.iter().map(|t| (t.delta_line, t.token_type)).collect();
+        assert_eq!(
+            types,
+            vec![
+                (0, TOKEN_METADATA_KEY),
+                (1, TOKEN_METADATA_KEY),
+                (0, TOKEN_METADATA_VALUE),
+                (1, TOKEN_METADATA_KEY),
+                (1, TOKEN_METADATA_VALUE),
+                (1, TOKEN_METADATA_KEY),
+            ]
+        );
     }
 }
@@ -0,0 +1,459 @@
+//! Server settings.
+//!
+//! Settings come from the `initializationOptions` of the `initialize`
+//! request and, for clients that support it, the `cooklang` section of the
+//! client configuration (pulled with `workspace/configuration` at startup and
+//! whenever `workspace/didChangeConfiguration` is received). Every field is
+//! optional; paths are relative to the workspace root. A `.cooklang.toml` in
+//! the workspace root overrides them (see [`crate::project_config`]). A
+//! setting with an invalid value is skipped and reported; the others apply.
+//!
+//! ```json
+//! {
+//!   "extensions": ["COMPONENT_MODIFIERS", "ADVANCED_UNITS"],
+//!   "aislePath": "shopping/aisle.conf",
+//!   "pantryPath": "shopping/pantry.conf",
+//!   "dataDirectory": ".cooklang",
+//!   "templateDirectory": ".cooklang/templates",
+//!   "locale": "de",
+//!   "lints": { "metadata": "error", "references": false, "long-step": "off" },
+//!   "completion": { "workspace": true, "aisle": true, "common": false },
+//!   "format": { "sectionStyle": "double", "compactQuantities": true }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use cooklang::Extensions;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tower_lsp::lsp_types::DiagnosticSeverity;
+
+use crate::data::DATA_DIR;
+use crate::templates::DEFAULT_TEMPLATE_DIR;
+
+/// Section of the client configuration holding the settings
+pub const SECTION: &str = "cooklang";
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    /// Parser extensions to enable; all of them when unset
+    pub extensions: Option<ExtensionsSetting>,
+    /// aisle.conf to use instead of looking in `config/` and the root
+    pub aisle_path: Option<PathBuf>,
+    /// pantry.conf to use instead of looking in `config/` and the root
+    pub pantry_path: Option<PathBuf>,
+    /// Directory with completion data overrides
+    pub data_directory: Option<PathBuf>,
+    /// Directory with recipe templates
+    pub template_directory: Option<PathBuf>,
+    /// Language of recipes without `locale` metadata
+    pub locale: Option<String>,
+    pub lints: LintSettings,
+    pub completion: CompletionSettings,
+    pub format: FormatSettings,
+}
+
+/// Parser extensions: a preset or the names of the extensions
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ExtensionsSetting {
+    /// `all`, `compat` (what other Cooklang parsers support) or `none`
+    /// (strict spec)
+    Preset(String),
+    List(Vec<String>),
+}
+
+impl ExtensionsSetting {
+    /// The extensions it enables, and the preset or names it doesn't know
+    /// (which enable nothing, or everything for a preset)
+    pub fn resolve(&self) -> (Extensions, Vec<&str>) {
+        match self {
+            Self::Preset(preset) => match preset.to_lowercase().as_str() {
+                "all" => (Extensions::all(), vec![]),
+                "compat" => (Extensions::COMPAT, vec![]),
+                "none" => (Extensions::empty(), vec![]),
+                _ => (Extensions::all(), vec![preset.as_str()]),
+            },
+            Self::List(names) => {
+                let mut unknown = Vec::new();
+                let extensions = names.iter().fold(Extensions::empty(), |extensions, name| {
+                    match Extensions::from_name(&name.to_uppercase()) {
+                        Some(extension) => extensions | extension,
+                        None => {
+                            unknown.push(name.as_str());
+                            extensions
+                        }
+                    }
+                });
+                (extensions, unknown)
+            }
+        }
+    }
+}
+
+/// Diagnostics the server adds to the parser's
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LintSettings {
+    /// Check the values of canonical metadata keys
+    pub metadata: LintLevel,
+    /// Report references to recipes that don't exist
+    pub references: LintLevel,
+    /// Report syntax that needs a disabled parser extension
+    pub portability: LintLevel,
+    /// Most sentences in a step before `long-step` reports it
+    pub max_step_sentences: usize,
+    /// Levels of the [lint rules](crate::lints), by name
+    #[serde(flatten)]
+    pub rules: BTreeMap<String, LintLevel>,
+}
+
+impl Default for LintSettings {
+    fn default() -> Self {
+        Self {
+            metadata: LintLevel::default(),
+            references: LintLevel::default(),
+            portability: LintLevel::default(),
+            max_step_sentences: 5,
+            rules: BTreeMap::new(),
+        }
+    }
+}
+
+impl LintSettings {
+    /// The level of the lint rule `name`
+    pub fn rule(&self, name: &str) -> LintLevel {
+        self.rules.get(name).copied().unwrap_or_default()
+    }
+}
+
+/// Whether a lint runs and how its diagnostics are reported: `true`/`false`,
+/// or `"off"`, `"hint"`, `"info"`, `"warning"` or `"error"`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "LintLevelValue")]
+pub enum LintLevel {
+    Off,
+    /// On, with the lint's own severity
+    #[default]
+    On,
+    Hint,
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LintLevelValue {
+    Enabled(bool),
+    Level(String),
+}
+
+impl TryFrom<LintLevelValue> for LintLevel {
+    type Error = String;
+
+    fn try_from(value: LintLevelValue) -> Result<Self, String> {
+        let level = match value {
+            LintLevelValue::Enabled(true) => return Ok(Self::On),
+            LintLevelValue::Enabled(false) => return Ok(Self::Off),
+            LintLevelValue::Level(level) => level,
+        };
+        match level.to_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "on" => Ok(Self::On),
+            "hint" => Ok(Self::Hint),
+            "info" | "information" => Ok(Self::Info),
+            "warn" | "warning" => Ok(Self::Warning),
+            "error" => Ok(Self::Error),
+            _ => Err(format!(
+                "unknown lint level `{level}`, expected `off`, `hint`, `info`, `warning` or `error`"
+            )),
+        }
+    }
+}
+
+impl LintLevel {
+    pub fn is_enabled(self) -> bool {
+        self != Self::Off
+    }
+
+    /// The severity to report the lint with, when it replaces the lint's own
+    pub fn severity(self) -> Option<DiagnosticSeverity> {
+        match self {
+            Self::Off | Self::On => None,
+            Self::Hint => Some(DiagnosticSeverity::HINT),
+            Self::Info => Some(DiagnosticSeverity::INFORMATION),
+            Self::Warning => Some(DiagnosticSeverity::WARNING),
+            Self::Error => Some(DiagnosticSeverity::ERROR),
+        }
+    }
+}
+
+/// Where completion suggestions come from, besides the current recipe
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CompletionSettings {
+    /// Other recipes in the workspace
+    pub workspace: bool,
+    /// aisle.conf
+    pub aisle: bool,
+    /// The built-in (or `.cooklang/`) lists of common items and units
+    pub common: bool,
+}
+
+impl Default for CompletionSettings {
+    fn default() -> Self {
+        Self {
+            workspace: true,
+            aisle: true,
+            common: true,
+        }
+    }
+}
+
+/// How the formatter writes recipes
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FormatSettings {
+    pub section_style: SectionStyle,
+    /// Write quantities without spaces around `%` (`{200%g}`)
+    pub compact_quantities: bool,
+}
+
+impl Default for FormatSettings {
+    fn default() -> Self {
+        Self {
+            section_style: SectionStyle::Keep,
+            compact_quantities: true,
+        }
+    }
+}
+
+/// How section headers are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SectionStyle {
+    /// As they are
+    Keep,
+    /// `= Dough`
+    Single,
+    /// `== Dough ==`
+    Double,
+}
+
+impl Settings {
+    /// Read settings from a JSON value, logging the invalid ones (see
+    /// [`Self::parse`])
+    pub fn from_json(value: serde_json::Value) -> Self {
+        let (settings, problems) = Self::parse(value);
+        for problem in problems {
+            tracing::warn!("{}", problem);
+        }
+        settings
+    }
+
+    /// Read settings from a JSON value. Settings with invalid values are
+    /// left at their defaults, and what was wrong with them is returned.
+    pub fn parse(mut value: serde_json::Value) -> (Self, Vec<String>) {
+        if value.is_null() {
+            return (Self::default(), Vec::new());
+        }
+        let mut problems = Vec::new();
+        if let Some(object) = value.as_object_mut() {
+            for (key, value) in object.iter_mut() {
+                match key.as_str() {
+                    "lints" => {
+                        retain_valid::<LintSettings>(value, "lints.", &mut problems);
+                        retain_lint_rules(value, &mut problems);
+                    }
+                    "completion" => {
+                        retain_valid::<CompletionSettings>(value, "completion.", &mut problems)
+                    }
+                    "format" => retain_valid::<FormatSettings>(value, "format.", &mut problems),
+                    _ => {}
+                }
+            }
+        }
+        retain_valid::<Self>(&mut value, "", &mut problems);
+        let settings = serde_json::from_value(value).unwrap_or_else(|err| {
+            problems.push(format!("Invalid settings: {err}"));
+            Self::default()
+        });
+        (settings, problems)
+    }
+
+    /// The parser extensions to enable. Unknown names are skipped.
+    pub fn extensions(&self) -> Extensions {
+        let Some(ref setting) = self.extensions else {
+            return Extensions::all();
+        };
+        let (extensions, unknown) = setting.resolve();
+        for name in unknown {
+            tracing::warn!("Unknown parser extension or preset: {}", name);
+        }
+        extensions
+    }
+
+    pub fn data_directory(&self) -> PathBuf {
+        self.data_directory
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DATA_DIR))
+    }
+
+    pub fn template_directory(&self) -> PathBuf {
+        self.template_directory
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_TEMPLATE_DIR))
+    }
+}
+
+/// Drop the keys of the `value` object that `T` can't read, saying why.
+/// Null values are dropped too, as if the key wasn't there.
+fn retain_valid<T: DeserializeOwned>(
+    value: &mut serde_json::Value,
+    prefix: &str,
+    problems: &mut Vec<String>,
+) {
+    let Some(object) = value.as_object_mut() else {
+        return;
+    };
+    object.retain(|key, value| {
+        if value.is_null() {
+            return false;
+        }
+        let single = serde_json::Map::from_iter([(key.clone(), value.clone())]);
+        match serde_json::from_value::<T>(single.into()) {
+            Ok(_) => true,
+            Err(err) => {
+                problems.push(format!("Invalid setting `{prefix}{key}`: {err}"));
+                false
+            }
+        }
+    });
+}
+
+/// Drop the keys of the `lints` object that are neither a lint setting nor
+/// a [lint rule](crate::lints), like `.cooklang.toml` does.
+fn retain_lint_rules(value: &mut serde_json::Value, problems: &mut Vec<String>) {
+    const FIELDS: &[&str] = &["metadata", "references", "portability", "maxStepSentences"];
+    let Some(object) = value.as_object_mut() else {
+        return;
+    };
+    object.retain(|key, _| {
+        if FIELDS.contains(&key.as_str()) || crate::lints::is_rule(key) {
+            return true;
+        }
+        problems.push(format!(
+            "Invalid setting `lints.{key}`: unknown lint rule `{key}`"
+        ));
+        false
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_partial_settings() {
+        let settings = Settings::from_json(json!({
+            "extensions": ["component_modifiers", "MODES", "bogus"],
+            "aislePath": "shopping/aisle.conf",
+            "lints": { "references": false },
+            "completion": { "common": false },
+        }));
+        assert_eq!(
+            settings.extensions(),
+            Extensions::COMPONENT_MODIFIERS | Extensions::MODES
+        );
+        assert_eq!(settings.aisle_path, Some("shopping/aisle.conf".into()));
+        assert!(settings.lints.metadata.is_enabled());
+        assert!(!settings.lints.references.is_enabled());
+        assert!(settings.completion.workspace);
+        assert!(!settings.completion.common);
+        assert_eq!(
+            settings.template_directory(),
+            PathBuf::from(DEFAULT_TEMPLATE_DIR)
+        );
+    }
+
+    #[test]
+    fn reads_lint_levels() {
+        let settings = Settings::from_json(json!({
+            "lints": {
+                "metadata": "error",
+                "references": "off",
+                "portability": true,
+                "long-step": "warning",
+                "maxStepSentences": 3,
+            },
+        }));
+        assert_eq!(settings.lints.metadata, LintLevel::Error);
+        assert_eq!(
+            settings.lints.metadata.severity(),
+            Some(DiagnosticSeverity::ERROR)
+        );
+        assert!(!settings.lints.references.is_enabled());
+        assert_eq!(settings.lints.portability, LintLevel::On);
+        assert_eq!(settings.lints.portability.severity(), None);
+        assert_eq!(settings.lints.rule("long-step"), LintLevel::Warning);
+        assert_eq!(settings.lints.rule("unused-cookware"), LintLevel::On);
+        assert_eq!(settings.lints.max_step_sentences, 3);
+
+        // An unknown level is skipped, the other settings still apply
+        let (settings, problems) = Settings::parse(json!({
+            "lints": { "metadata": "loud", "references": "off" },
+        }));
+        assert_eq!(settings.lints.metadata, LintLevel::On);
+        assert!(!settings.lints.references.is_enabled());
+        assert_eq!(
+            problems,
+            [
+                "Invalid setting `lints.metadata`: unknown lint level `loud`, \
+              expected `off`, `hint`, `info`, `warning` or `error`"
+            ]
+        );
+
+        // So is a misspelled rule
+        let (settings, problems) = Settings::parse(json!({
+            "lints": { "long-steps": "off", "unused-cookware": "off" },
+        }));
+        assert!(!settings.lints.rules.contains_key("long-steps"));
+        assert!(!settings.lints.rule("unused-cookware").is_enabled());
+        assert_eq!(
+            problems,
+            ["Invalid setting `lints.long-steps`: unknown lint rule `long-steps`"]
+        );
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(
+            Settings::from_json(serde_json::Value::Null),
+            Settings::default()
+        );
+        assert_eq!(Settings::default().extensions(), Extensions::all());
+        let preset = |name: &str| Settings::from_json(json!({ "extensions": name })).extensions();
+        assert_eq!(preset("none"), Extensions::empty());
+        assert_eq!(preset("compat"), Extensions::COMPAT);
+        // Wrong types fall back to the defaults, and are reported
+        let (settings, problems) = Settings::parse(json!({
+            "lints": "off",
+            "format": { "sectionStyle": "triple", "compactQuantities": false },
+            "aislePath": "aisle.conf",
+            "dataDirectory": null,
+        }));
+        assert_eq!(settings.lints, LintSettings::default());
+        assert_eq!(settings.format.section_style, SectionStyle::Keep);
+        assert!(!settings.format.compact_quantities);
+        assert_eq!(settings.aisle_path, Some("aisle.conf".into()));
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("Invalid setting `format.sectionStyle`"));
+        assert!(problems[1].starts_with("Invalid setting `lints`"));
+        assert_eq!(Settings::parse(json!("off")).1.len(), 1);
+    }
+}
@@ -0,0 +1,470 @@
+//! The `cooklang.shoppingList` command.
+//!
+//! Aggregates the ingredients of one or more recipes (or `.menu` files),
+//! following recipe references and scaling them, subtracts what the pantry
+//! already has, and groups what is left by the aisle.conf categories.
+
+use std::path::{Path, PathBuf};
+
+use cooklang::ingredient_list::IngredientList;
+use cooklang::quantity::GroupedQuantity;
+use cooklang::Recipe;
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::Url;
+
+use crate::document::{parse_recipe, parser};
+use crate::references;
+use crate::state::ServerState;
+
+/// Command identifier advertised in `executeCommandProvider`.
+pub const COMMAND: &str = "cooklang.shoppingList";
+
+/// Category for ingredients that are not in aisle.conf.
+const OTHER_CATEGORY: &str = "other";
+
+/// How deep recipe references are followed.
+const MAX_REFERENCE_DEPTH: usize = 8;
+
+/// Arguments of the command (the first element of `arguments`).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShoppingListParams {
+    /// Recipes or menus to shop for
+    pub recipes: Vec<RecipeEntry>,
+    /// Output format, Markdown by default
+    #[serde(default)]
+    pub format: OutputFormat,
+}
+
+/// A recipe to include, either a bare URI or a URI with scaling.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RecipeEntry {
+    Uri(Url),
+    Scaled {
+        uri: Url,
+        /// Scale to this many servings
+        #[serde(default)]
+        servings: Option<u32>,
+        /// Scale by this factor (ignored when `servings` is given)
+        #[serde(default)]
+        scale: Option<f64>,
+    },
+}
+
+impl RecipeEntry {
+    pub fn uri(&self) -> &Url {
+        match self {
+            RecipeEntry::Uri(uri) | RecipeEntry::Scaled { uri, .. } => uri,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Markdown,
+    Json,
+}
+
+/// The aggregated list, grouped by aisle category.
+#[derive(Debug, Default, Serialize)]
+pub struct ShoppingList {
+    pub categories: Vec<Category>,
+    /// Recipes (or references) that could not be loaded
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Category {
+    pub name: String,
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Item {
+    pub name: String,
+    /// Combined quantity, e.g. "1.5 kg, 2 bags"; empty if none was given
+    pub quantity: String,
+}
+
+/// Run the command and render the result in the requested format.
+pub fn execute(
+    params: ShoppingListParams,
+    state: &ServerState,
+    workspace_root: Option<&Path>,
+) -> serde_json::Value {
+    let list = build(&params.recipes, state, workspace_root);
+    match params.format {
+        OutputFormat::Markdown => serde_json::Value::String(to_markdown(&list)),
+        OutputFormat::Json => serde_json::to_value(&list).unwrap_or_default(),
+    }
+}
+
+/// Build the shopping list for `entries`.
+pub fn build(
+    entries: &[RecipeEntry],
+    state: &ServerState,
+    workspace_root: Option<&Path>,
+) -> ShoppingList {
+    let parser = parser();
+    let converter = parser.converter();
+    let mut ingredients = IngredientList::new();
+    let mut errors = Vec::new();
+
+    for entry in entries {
+        let uri = entry.uri();
+        let (servings, scale) = match entry {
+            RecipeEntry::Uri(_) => (None, None),
+            RecipeEntry::Scaled {
+                servings, scale, ..
+            } => (*servings, *scale),
+        };
+
+        let Some(mut recipe) = state.read_source(uri).and_then(|src| parse_recipe(&src)) else {
+            errors.push(format!("Could not load {}", uri));
+            continue;
+        };
+
+        if let Some(servings) = servings {
+            if let Err(err) = recipe.scale_to_servings(servings, converter) {
+                errors.push(format!("{}: {}", uri, err));
+            }
+        } else if let Some(scale) = scale {
+            recipe.scale(scale, converter);
+        }
+
+        let doc_dir = references::document_dir(uri);
+        let mut chain: Vec<PathBuf> = uri.to_file_path().ok().map(canonical).into_iter().collect();
+        add_recipe(
+            &mut ingredients,
+            &recipe,
+            doc_dir.as_deref(),
+            workspace_root,
+            state,
+            &mut chain,
+            &mut errors,
+        );
+    }
+
+    if let Ok(guard) = state.pantry_config.read() {
+        if let Some(ref pantry) = *guard {
+            ingredients = ingredients.subtract_pantry(pantry, converter);
+        }
+    }
+
+    ShoppingList {
+        categories: categorize(ingredients, state),
+        errors,
+    }
+}
+
+/// The path with `.` and `..` resolved, so the same file is recognised
+/// however it was reached
+fn canonical(path: PathBuf) -> PathBuf {
+    std::fs::canonicalize(&path).unwrap_or(path)
+}
+
+/// Add a recipe's own ingredients to the list, then recurse into the
+/// recipes it references, scaled by the quantity on each reference.
+/// `chain` holds the files of the recipes being added, outermost first:
+/// a reference back to one of them is a cycle and isn't followed.
+fn add_recipe(
+    list: &mut IngredientList,
+    recipe: &Recipe,
+    doc_dir: Option<&Path>,
+    workspace_root: Option<&Path>,
+    state: &ServerState,
+    chain: &mut Vec<PathBuf>,
+    errors: &mut Vec<String>,
+) {
+    list.add_recipe(recipe, parser().converter(), false);
+
+    for ingredient in &recipe.ingredients {
+        let Some(ref reference) = ingredient.reference else {
+            continue;
+        };
+        let path = reference.path("/");
+
+        if chain.len() > MAX_REFERENCE_DEPTH {
+            errors.push(format!("Reference nested too deeply: {}", path));
+            continue;
+        }
+
+        let Some(file) = references::resolve(reference, doc_dir, workspace_root) else {
+            errors.push(format!("Referenced recipe not found: {}", path));
+            continue;
+        };
+        let file = canonical(file);
+        if chain.contains(&file) {
+            errors.push(format!("Reference cycle: {}", path));
+            continue;
+        }
+        let Some(mut referenced) = references::load_recipe(&file, state) else {
+            errors.push(format!("Could not load {}", file.display()));
+            continue;
+        };
+
+        references::scale_for_reference(&mut referenced, ingredient);
+        chain.push(file.clone());
+        add_recipe(
+            list,
+            &referenced,
+            file.parent(),
+            workspace_root,
+            state,
+            chain,
+            errors,
+        );
+        chain.pop();
+    }
+}
+
+/// Group ingredients by aisle.conf category, in the order the categories
+/// appear there, using the common name for aliases. Anything not in
+/// aisle.conf ends up in a trailing "other" category.
+fn categorize(list: IngredientList, state: &ServerState) -> Vec<Category> {
+    let parser = parser();
+    let converter = parser.converter();
+    let aisle = state.get_aisle_ingredients();
+
+    let mut grouped: Vec<(String, Vec<(String, GroupedQuantity)>)> = Vec::new();
+    for ingredient in &aisle {
+        if !grouped.iter().any(|(name, _)| name == &ingredient.category) {
+            grouped.push((ingredient.category.clone(), Vec::new()));
+        }
+    }
+    grouped.push((OTHER_CATEGORY.to_string(), Vec::new()));
+
+    for (name, quantity) in list {
+        let (common_name, category) = aisle
+            .iter()
+            .find(|i| i.name.eq_ignore_ascii_case(&name))
+            .map(|i| (i.common_name.clone(), i.category.as_str()))
+            .unwrap_or((name, OTHER_CATEGORY));
+
+        let Some((_, items)) = grouped.iter_mut().find(|(name, _)| name == category) else {
+            continue;
+        };
+        match items.iter_mut().find(|(n, _)| n == &common_name) {
+            Some((_, existing)) => existing.merge(&quantity, converter),
+            None => items.push((common_name, quantity)),
+        }
+    }
+
+    grouped
+        .into_iter()
+        .filter(|(_, items)| !items.is_empty())
+        .map(|(name, items)| Category {
+            name,
+            items: items
+                .into_iter()
+                .map(|(name, quantity)| Item {
+                    name,
+                    quantity: quantity.to_string(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Render the list as Markdown: one heading per category, one bullet per item.
+pub fn to_markdown(list: &ShoppingList) -> String {
+    let mut out = String::new();
+
+    for category in &list.categories {
+        out.push_str(&format!("## {}\n\n", category.name));
+        for item in &category.items {
+            if item.quantity.is_empty() {
+                out.push_str(&format!("- {}\n", item.name));
+            } else {
+                out.push_str(&format!("- {}: {}\n", item.name, item.quantity));
+            }
+        }
+        out.push('\n');
+    }
+
+    if !list.errors.is_empty() {
+        out.push_str("## Problems\n\n");
+        for error in &list.errors {
+            out.push_str(&format!("- {}\n", error));
+        }
+        out.push('\n');
+    }
+
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AisleConfig;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn uri(path: &Path) -> Url {
+        Url::from_file_path(path).unwrap()
+    }
+
+    fn names(list: &ShoppingList, category: &str) -> Vec<(String, String)> {
+        list.categories
+            .iter()
+            .find(|c| c.name == category)
+            .map(|c| {
+                c.items
+                    .iter()
+                    .map(|i| (i.name.clone(), i.quantity.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn aggregates_and_scales_recipes() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("Pancakes.cook"),
+            ">> servings: 2\n\nMix @flour{200%g} and @milk{300%ml}.",
+        )
+        .unwrap();
+        fs::write(root.join("Bread.cook"), "Knead @flour{500%g} with @salt.").unwrap();
+
+        let state = ServerState::new();
+        let entries = vec![
+            RecipeEntry::Scaled {
+                uri: uri(&root.join("Pancakes.cook")),
+                servings: Some(4),
+                scale: None,
+            },
+            RecipeEntry::Uri(uri(&root.join("Bread.cook"))),
+        ];
+        let list = build(&entries, &state, Some(root));
+
+        assert!(list.errors.is_empty(), "{:?}", list.errors);
+        let other = names(&list, "other");
+        assert!(
+            other.contains(&("flour".into(), "900 g".into())),
+            "{other:?}"
+        );
+        assert!(
+            other.contains(&("milk".into(), "600 ml".into())),
+            "{other:?}"
+        );
+        assert!(other.contains(&("salt".into(), "".into())), "{other:?}");
+    }
+
+    #[test]
+    fn follows_menu_references() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("sauces")).unwrap();
+        fs::write(
+            root.join("sauces/Pesto.cook"),
+            ">> servings: 2\n\nBlend @basil{50%g}.",
+        )
+        .unwrap();
+        fs::write(
+            root.join("Week.menu"),
+            "== Monday ==\n@./sauces/Pesto{4%servings}\n@./Missing{}\n",
+        )
+        .unwrap();
+
+        let state = ServerState::new();
+        let entries = vec![RecipeEntry::Uri(uri(&root.join("Week.menu")))];
+        let list = build(&entries, &state, Some(root));
+
+        assert_eq!(
+            names(&list, "other"),
+            vec![("basil".to_string(), "100 g".to_string())]
+        );
+        assert_eq!(list.errors, vec!["Referenced recipe not found: ./Missing"]);
+    }
+
+    #[test]
+    fn stops_at_reference_cycles() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("Bread.cook"),
+            "Knead @flour{500%g}, serve with @./Butter{}.",
+        )
+        .unwrap();
+        fs::write(
+            root.join("Butter.cook"),
+            "Churn @cream{200%ml}, spread on @./Bread{}.",
+        )
+        .unwrap();
+
+        let state = ServerState::new();
+        let entries = vec![RecipeEntry::Uri(uri(&root.join("Bread.cook")))];
+        let list = build(&entries, &state, Some(root));
+
+        assert_eq!(
+            names(&list, "other"),
+            vec![
+                ("flour".to_string(), "500 g".to_string()),
+                ("cream".to_string(), "200 ml".to_string()),
+            ]
+        );
+        assert_eq!(list.errors, vec!["Reference cycle: ./Bread"]);
+    }
+
+    #[test]
+    fn groups_by_aisle_and_subtracts_pantry() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("Soup.cook"),
+            "Chop @yellow onion{2} and @carrots{3}, add @rice{1%kg} and @salt.",
+        )
+        .unwrap();
+
+        let state = ServerState::new();
+        *state.aisle_config.write().unwrap() =
+            AisleConfig::parse("[produce]\ncarrots\nonions|yellow onion\n");
+        *state.pantry_config.write().unwrap() =
+            crate::state::parse_pantry_config("[pantry]\nsalt = \"unlimited\"\nrice = \"400%g\"\n");
+
+        let entries = vec![RecipeEntry::Uri(uri(&root.join("Soup.cook")))];
+        let list = build(&entries, &state, Some(root));
+
+        assert_eq!(list.categories[0].name, "produce");
+        assert_eq!(
+            names(&list, "produce"),
+            vec![
+                ("onions".to_string(), "2".to_string()),
+                ("carrots".to_string(), "3".to_string())
+            ]
+        );
+        // Units differ (kg vs g), so the pantry can't cover the rice; salt is unlimited.
+        assert_eq!(
+            names(&list, "other"),
+            vec![("rice".to_string(), "1 kg".to_string())]
+        );
+    }
+
+    #[test]
+    fn markdown_output() {
+        let list = ShoppingList {
+            categories: vec![Category {
+                name: "dairy".into(),
+                items: vec![
+                    Item {
+                        name: "milk".into(),
+                        quantity: "1 l".into(),
+                    },
+                    Item {
+                        name: "butter".into(),
+                        quantity: String::new(),
+                    },
+                ],
+            }],
+            errors: vec![],
+        };
+        assert_eq!(to_markdown(&list), "## dairy\n\n- milk: 1 l\n- butter\n");
+    }
+}
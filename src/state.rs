@@ -1,10 +1,18 @@
-use std::path::Path;
-use std::sync::RwLock;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
+use cooklang::pantry::PantryConf;
 use dashmap::DashMap;
 use tower_lsp::lsp_types::Url;
 
+use crate::data::CompletionData;
 use crate::document::Document;
+use crate::i18n::Language;
+use crate::index::{self, IndexEntry, QuantityUse};
+use crate::semantic_tokens::TokenCache;
+use crate::settings::{CompletionSettings, Settings};
+use crate::templates::{self, RecipeTemplate};
 
 /// An ingredient from the aisle configuration with its category
 #[derive(Debug, Clone)]
@@ -53,53 +61,214 @@ impl AisleConfig {
         Some(AisleConfig { ingredients })
     }
 
-    /// Load aisle.conf from a workspace path
+    /// Load an aisle.conf file
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        tracing::info!("Loading aisle.conf from {:?}", path);
+        Self::parse(&content)
+    }
+
+    /// Load aisle.conf from a workspace path: `config/aisle.conf` (the
+    /// standard cooklang location), or else the root one
     pub fn load_from_workspace(workspace_path: &Path) -> Option<Self> {
-        // Check for config/aisle.conf (standard cooklang location)
-        let config_path = workspace_path.join("config").join("aisle.conf");
-        if config_path.exists() {
-            if let Ok(content) = std::fs::read_to_string(&config_path) {
-                tracing::info!("Loading aisle.conf from {:?}", config_path);
-                return Self::parse(&content);
-            }
-        }
+        let path = config_paths(workspace_path, None, "aisle.conf")
+            .into_iter()
+            .find(|path| path.exists())?;
+        Self::load(&path)
+    }
+}
 
-        // Also check root aisle.conf
-        let root_path = workspace_path.join("aisle.conf");
-        if root_path.exists() {
-            if let Ok(content) = std::fs::read_to_string(&root_path) {
-                tracing::info!("Loading aisle.conf from {:?}", root_path);
-                return Self::parse(&content);
-            }
-        }
+/// Parse a pantry.conf file content
+/// Uses lenient parsing to skip errors and continue with valid entries
+pub fn parse_pantry_config(content: &str) -> Option<PantryConf> {
+    let result = cooklang::pantry::parse_lenient(content);
+    let (pantry_conf, warnings) = result.into_result().ok()?;
+
+    for warning in warnings.iter() {
+        tracing::warn!("pantry.conf warning: {}", warning);
+    }
+
+    Some(pantry_conf)
+}
 
-        None
+/// The file the settings point at, or else `name` in `config/` and in the
+/// workspace root
+fn config_paths(workspace_path: &Path, setting: Option<PathBuf>, name: &str) -> Vec<PathBuf> {
+    match setting {
+        Some(path) => vec![workspace_path.join(path)],
+        None => vec![
+            workspace_path.join("config").join(name),
+            workspace_path.join(name),
+        ],
     }
 }
 
+/// Load pantry.conf from a workspace path, looking in the same places as aisle.conf
+pub fn load_pantry_from_workspace(workspace_path: &Path) -> Option<PantryConf> {
+    let path = config_paths(workspace_path, None, "pantry.conf")
+        .into_iter()
+        .find(|path| path.exists())?;
+    let content = std::fs::read_to_string(&path).ok()?;
+    tracing::info!("Loading pantry.conf from {:?}", path);
+    parse_pantry_config(&content)
+}
+
 /// Thread-safe server state
 pub struct ServerState {
     pub documents: DashMap<Url, Document>,
+    /// Edits not parsed yet: the latest version of a document and its text.
+    /// They are parsed when the document is next needed.
+    pending_changes: DashMap<Url, (i32, String)>,
+    /// Current settings
+    pub settings: RwLock<Settings>,
     /// Parsed aisle configuration for ingredient suggestions
     pub aisle_config: RwLock<Option<AisleConfig>>,
+    /// Parsed pantry configuration (what is already in stock)
+    pub pantry_config: RwLock<Option<PantryConf>>,
+    /// Summary of every recipe in the workspace (and any open document)
+    pub index: DashMap<Url, IndexEntry>,
+    /// Last semantic tokens sent per document
+    pub semantic_tokens: TokenCache,
+    /// Directory with the workspace's completion data overrides
+    pub data_dir: RwLock<Option<PathBuf>>,
+    /// Common ingredients, cookware and units offered by completion, per
+    /// language (loaded when first needed)
+    pub completion_data: DashMap<Language, Arc<CompletionData>>,
+    /// Language of recipes without `locale` metadata
+    pub language: RwLock<Language>,
+    /// Recipe templates offered in empty documents
+    pub templates: RwLock<Vec<RecipeTemplate>>,
 }
 
 impl ServerState {
     pub fn new() -> Self {
         Self {
             documents: DashMap::new(),
+            pending_changes: DashMap::new(),
+            settings: RwLock::new(Settings::default()),
             aisle_config: RwLock::new(None),
+            pantry_config: RwLock::new(None),
+            index: DashMap::new(),
+            semantic_tokens: TokenCache::new(),
+            data_dir: RwLock::new(None),
+            completion_data: DashMap::new(),
+            language: RwLock::new(Language::default()),
+            templates: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// A copy of the current settings
+    pub fn settings(&self) -> Settings {
+        self.settings
+            .read()
+            .map(|settings| settings.clone())
+            .unwrap_or_default()
+    }
+
+    /// Where completion suggestions may come from
+    pub fn completion_sources(&self) -> CompletionSettings {
+        self.settings
+            .read()
+            .map(|settings| settings.completion.clone())
+            .unwrap_or_default()
+    }
+
+    /// Switch to new settings and parse the open documents again with the
+    /// extensions they enable
+    pub fn apply_settings(&self, settings: Settings) {
+        let extensions = settings.extensions();
+        if let Ok(mut guard) = self.settings.write() {
+            *guard = settings;
+        }
+        for mut entry in self.documents.iter_mut() {
+            entry.value_mut().set_extensions(extensions);
         }
     }
 
-    /// Load aisle configuration from a workspace path
+    /// Load aisle configuration from a workspace path (or the file the
+    /// settings point at)
     pub fn load_aisle_config(&self, workspace_path: &Path) {
-        if let Some(config) = AisleConfig::load_from_workspace(workspace_path) {
-            let count = config.ingredients.len();
-            if let Ok(mut guard) = self.aisle_config.write() {
-                *guard = Some(config);
-                tracing::info!("Loaded {} ingredients from aisle.conf", count);
-            }
+        let config = match self.settings().aisle_path {
+            Some(path) => AisleConfig::load(&workspace_path.join(path)),
+            None => AisleConfig::load_from_workspace(workspace_path),
+        };
+        if let Some(ref config) = config {
+            tracing::info!(
+                "Loaded {} ingredients from aisle.conf",
+                config.ingredients.len()
+            );
+        }
+        if let Ok(mut guard) = self.aisle_config.write() {
+            *guard = config;
+        }
+    }
+
+    /// Where the aisle configuration of a workspace is looked for
+    pub fn aisle_config_paths(&self, workspace_path: &Path) -> Vec<PathBuf> {
+        config_paths(workspace_path, self.settings().aisle_path, "aisle.conf")
+    }
+
+    /// Where the pantry configuration of a workspace is looked for
+    pub fn pantry_config_paths(&self, workspace_path: &Path) -> Vec<PathBuf> {
+        config_paths(workspace_path, self.settings().pantry_path, "pantry.conf")
+    }
+
+    /// Load pantry configuration from a workspace path (or the file the
+    /// settings point at)
+    pub fn load_pantry_config(&self, workspace_path: &Path) {
+        let config = match self.settings().pantry_path {
+            Some(path) => std::fs::read_to_string(workspace_path.join(path))
+                .ok()
+                .and_then(|content| parse_pantry_config(&content)),
+            None => load_pantry_from_workspace(workspace_path),
+        };
+        if let Some(ref config) = config {
+            tracing::info!(
+                "Loaded {} items from pantry.conf",
+                config.all_items().count()
+            );
+        }
+        if let Ok(mut guard) = self.pantry_config.write() {
+            *guard = config;
+        }
+    }
+
+    /// Use completion data overrides from a data directory (the built-in
+    /// lists when `dir` is `None`)
+    pub fn load_completion_data(&self, dir: Option<&Path>) {
+        if let Ok(mut guard) = self.data_dir.write() {
+            *guard = dir.map(Path::to_path_buf);
+        }
+        self.completion_data.clear();
+    }
+
+    /// The lists offered by completion in `language`
+    pub fn completion_data(&self, language: Language) -> Arc<CompletionData> {
+        self.completion_data
+            .entry(language)
+            .or_insert_with(|| {
+                let dir = self.data_dir.read().ok().and_then(|dir| dir.clone());
+                Arc::new(CompletionData::load(dir.as_deref(), language))
+            })
+            .clone()
+    }
+
+    /// The language of `doc`: its `locale` metadata, or the default
+    pub fn language_for(&self, doc: &Document) -> Language {
+        Language::of_document(doc).unwrap_or_else(|| {
+            self.language
+                .read()
+                .map(|language| *language)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Load recipe templates from a template directory (none when `dir` is
+    /// `None`)
+    pub fn load_templates(&self, dir: Option<&Path>) {
+        let loaded = dir.map(templates::load_templates).unwrap_or_default();
+        if let Ok(mut guard) = self.templates.write() {
+            *guard = loaded;
         }
     }
 
@@ -114,23 +283,168 @@ impl ServerState {
     }
 
     pub fn open_document(&self, uri: Url, version: i32, content: String) {
-        let doc = Document::new(uri.clone(), version, content);
-        self.documents.insert(uri, doc);
+        let extensions = self.settings().extensions();
+        let doc = Document::with_extensions(uri.clone(), version, content, extensions);
+        self.pending_changes.remove(&uri);
+        self.documents.insert(uri.clone(), doc);
+        self.reindex(&uri);
     }
 
+    /// Apply a new version of a document. A version no newer than the one
+    /// parsed is ignored: two flushes of pending changes may race, and the
+    /// older one must not land last.
     pub fn update_document(&self, uri: &Url, version: i32, content: String) {
         if let Some(mut doc) = self.documents.get_mut(uri) {
+            if version <= doc.version {
+                tracing::debug!("Ignoring stale version {} of {}", version, uri);
+                return;
+            }
             doc.update(version, content);
         }
+        self.reindex(uri);
+    }
+
+    /// Keep a new version of a document, to be parsed when it is next needed
+    /// (see [`Self::get_document`]), so a burst of edits is parsed once.
+    /// Versions older than the one already known are dropped: notifications
+    /// may be handled out of order.
+    pub fn queue_change(&self, uri: &Url, version: i32, content: String) {
+        let known = self
+            .pending_changes
+            .get(uri)
+            .map(|pending| pending.0)
+            .or_else(|| self.documents.get(uri).map(|doc| doc.version));
+        if known.is_some_and(|known| version <= known) {
+            tracing::debug!("Dropping stale version {} of {}", version, uri);
+            return;
+        }
+        self.pending_changes.insert(uri.clone(), (version, content));
+    }
+
+    /// The latest version of a document, parsed or not
+    pub fn latest_version(&self, uri: &Url) -> Option<i32> {
+        self.pending_changes
+            .get(uri)
+            .map(|pending| pending.0)
+            .or_else(|| self.documents.get(uri).map(|doc| doc.version))
+    }
+
+    /// Parse the pending change to a document, if there is one
+    pub fn flush_changes(&self, uri: &Url) {
+        if let Some((_, (version, content))) = self.pending_changes.remove(uri) {
+            self.update_document(uri, version, content);
+        }
     }
 
     pub fn close_document(&self, uri: &Url) {
+        self.pending_changes.remove(uri);
         self.documents.remove(uri);
+        self.semantic_tokens.remove(uri);
+        // Unsaved edits are gone; go back to what is on disk
+        self.reindex(uri);
+    }
+
+    /// Rebuild the index from the recipe files under `workspace_path`
+    pub fn index_workspace(&self, workspace_path: &Path) {
+        self.index.clear();
+        for path in index::recipe_files(workspace_path) {
+            if let Ok(uri) = Url::from_file_path(&path) {
+                self.reindex(&uri);
+            }
+        }
+        // Open documents outside the workspace are still indexed
+        let open: Vec<Url> = self.documents.iter().map(|e| e.key().clone()).collect();
+        for uri in open {
+            self.reindex(&uri);
+        }
+        tracing::info!("Indexed {} recipes", self.index.len());
+    }
+
+    /// Refresh the index entry for `uri` from the open document, or the file
+    /// on disk if it isn't open. Files that no longer exist are dropped.
+    pub fn reindex(&self, uri: &Url) {
+        let entry = if let Some(doc) = self.documents.get(uri) {
+            doc.parse_result
+                .as_ref()
+                .map(|result| IndexEntry::from_recipe(&result.recipe))
+                .unwrap_or_default()
+        } else {
+            match uri
+                .to_file_path()
+                .ok()
+                .and_then(|p| std::fs::read_to_string(p).ok())
+            {
+                Some(content) => IndexEntry::from_source(&content),
+                None => {
+                    self.index.remove(uri);
+                    return;
+                }
+            }
+        };
+        self.index.insert(uri.clone(), entry);
+    }
+
+    /// Every use of the ingredient `name` across the workspace, with the
+    /// recipe it is in
+    pub fn ingredient_uses(&self, name: &str) -> Vec<(Url, QuantityUse)> {
+        let name = name.to_lowercase();
+        self.index
+            .iter()
+            .flat_map(|entry| {
+                entry
+                    .value()
+                    .ingredients
+                    .iter()
+                    .filter(|u| u.name == name)
+                    .map(|u| (entry.key().clone(), u.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Every named timer across the workspace
+    pub fn timer_uses(&self) -> Vec<QuantityUse> {
+        self.index
+            .iter()
+            .flat_map(|entry| entry.value().timers.clone())
+            .collect()
+    }
+
+    /// Every tag used in the workspace and the recipes using it
+    pub fn tag_index(&self) -> BTreeMap<String, Vec<Url>> {
+        let mut tags: BTreeMap<String, Vec<Url>> = BTreeMap::new();
+        for entry in self.index.iter() {
+            for tag in &entry.value().tags {
+                tags.entry(tag.clone())
+                    .or_default()
+                    .push(entry.key().clone());
+            }
+        }
+        for uris in tags.values_mut() {
+            uris.sort();
+            uris.dedup();
+        }
+        tags
     }
 
+    /// An open document, with its pending changes parsed
     pub fn get_document(&self, uri: &Url) -> Option<dashmap::mapref::one::Ref<'_, Url, Document>> {
+        self.flush_changes(uri);
         self.documents.get(uri)
     }
+
+    /// Get the source of a recipe: the editor's version if the document is
+    /// open, otherwise the file on disk.
+    pub fn read_source(&self, uri: &Url) -> Option<String> {
+        if let Some(pending) = self.pending_changes.get(uri) {
+            return Some(pending.1.clone());
+        }
+        if let Some(doc) = self.documents.get(uri) {
+            return Some(doc.content.clone());
+        }
+        let path = uri.to_file_path().ok()?;
+        std::fs::read_to_string(path).ok()
+    }
 }
 
 impl Default for ServerState {
@@ -231,4 +545,119 @@ carrot
             .count();
         assert_eq!(apple_count, 1);
     }
+
+    #[test]
+    fn test_settings_reparse_open_documents() {
+        let state = ServerState::new();
+        let uri = Url::parse("file:///a.cook").unwrap();
+        state.open_document(uri.clone(), 1, "Mix @flour|farina{}.".into());
+        let names = |state: &ServerState| -> Vec<String> {
+            let doc = state.get_document(&uri).unwrap();
+            let recipe = &doc.parse_result.as_ref().unwrap().recipe;
+            recipe.ingredients.iter().map(|i| i.name.clone()).collect()
+        };
+        assert_eq!(names(&state), ["flour"]);
+
+        // Without the alias extension the `|` is part of the name
+        state.apply_settings(Settings::from_json(serde_json::json!({ "extensions": [] })));
+        assert_eq!(names(&state), ["flour|farina"]);
+    }
+
+    #[test]
+    fn test_queued_changes_are_parsed_when_needed() {
+        let state = ServerState::new();
+        let uri = Url::parse("file:///a.cook").unwrap();
+        state.open_document(uri.clone(), 1, "Add @salt{}.".into());
+
+        state.queue_change(&uri, 3, "Add @pepper{}.".into());
+        // Handled late: older than what we have
+        state.queue_change(&uri, 2, "Add @sugar{}.".into());
+        assert_eq!(state.latest_version(&uri), Some(3));
+        assert_eq!(state.documents.get(&uri).unwrap().version, 1);
+        assert_eq!(state.read_source(&uri).as_deref(), Some("Add @pepper{}."));
+
+        let doc = state.get_document(&uri).unwrap();
+        assert_eq!(doc.version, 3);
+        let recipe = &doc.parse_result.as_ref().unwrap().recipe;
+        assert_eq!(recipe.ingredients[0].name, "pepper");
+        drop(doc);
+
+        // A flush that lost the race to a newer one changes nothing
+        state.update_document(&uri, 2, "Add @sugar{}.".into());
+        assert_eq!(state.get_document(&uri).unwrap().content, "Add @pepper{}.");
+    }
+
+    #[test]
+    fn test_settings_choose_config_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("aisle.conf"), "[produce]\nonion\n").unwrap();
+        std::fs::create_dir(dir.path().join("shop")).unwrap();
+        std::fs::write(dir.path().join("shop/aisle.conf"), "[dairy]\nmilk\n").unwrap();
+
+        let state = ServerState::new();
+        state.load_aisle_config(dir.path());
+        assert_eq!(state.get_aisle_ingredients()[0].name, "onion");
+        assert_eq!(
+            state.aisle_config_paths(dir.path()),
+            vec![
+                dir.path().join("config/aisle.conf"),
+                dir.path().join("aisle.conf")
+            ]
+        );
+
+        state.apply_settings(Settings::from_json(
+            serde_json::json!({ "aislePath": "shop/aisle.conf" }),
+        ));
+        state.load_aisle_config(dir.path());
+        assert_eq!(state.get_aisle_ingredients()[0].name, "milk");
+        assert_eq!(
+            state.aisle_config_paths(dir.path()),
+            vec![dir.path().join("shop/aisle.conf")]
+        );
+    }
+
+    #[test]
+    fn test_tag_index_follows_open_documents() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let soup = dir.path().join("Soup.cook");
+        std::fs::write(&soup, ">> tags: quick, winter\n\nBoil @water.").unwrap();
+        std::fs::write(
+            dir.path().join("Salad.cook"),
+            ">> tags: quick\n\nMix @lettuce.",
+        )
+        .unwrap();
+
+        let state = ServerState::new();
+        state.index_workspace(dir.path());
+        let tags = state.tag_index();
+        assert_eq!(tags["quick"].len(), 2);
+        assert_eq!(tags["winter"].len(), 1);
+
+        // Unsaved edits are reflected, and dropped again when closed
+        let uri = Url::from_file_path(&soup).unwrap();
+        state.open_document(uri.clone(), 1, ">> tags: summer\n\nBoil @water.".into());
+        let tags = state.tag_index();
+        assert_eq!(tags["quick"].len(), 1);
+        assert!(!tags.contains_key("winter"));
+        assert_eq!(tags["summer"], vec![uri.clone()]);
+
+        state.close_document(&uri);
+        assert!(state.tag_index().contains_key("winter"));
+    }
+
+    #[test]
+    fn test_pantry_config_parse() {
+        let content = r#"
+[freezer]
+spinach = "1%kg"
+
+[pantry]
+salt = "unlimited"
+rice = "5%kg"
+"#;
+        let config = parse_pantry_config(content).unwrap();
+        assert!(config.has_ingredient("spinach"));
+        assert!(config.has_ingredient("rice"));
+        assert!(!config.has_ingredient("butter"));
+    }
 }
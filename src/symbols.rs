@@ -1,9 +1,14 @@
 use tower_lsp::lsp_types::{DocumentSymbol, DocumentSymbolResponse, Range, SymbolKind};
 
 use crate::document::Document;
+use crate::menu;
 
 #[allow(deprecated)] // DocumentSymbol::deprecated is deprecated but required
 pub fn get_document_symbols(doc: &Document) -> Option<DocumentSymbolResponse> {
+    if menu::is_menu(&doc.uri) {
+        return Some(DocumentSymbolResponse::Nested(menu::get_menu_symbols(doc)));
+    }
+
     let parse_result = doc.parse_result.as_ref()?;
     let recipe = &parse_result.recipe;
 
@@ -0,0 +1,98 @@
+//! Recipe templates offered when starting a new recipe.
+//!
+//! Templates are the `.cook` files in the workspace's template directory
+//! (`.cooklang/templates` unless the client configures another one); a few
+//! examples to start from are in `docs/templates/`.
+
+use std::path::Path;
+
+/// Template directory used when the client doesn't configure one, relative
+/// to the workspace root
+pub const DEFAULT_TEMPLATE_DIR: &str = ".cooklang/templates";
+
+/// A recipe template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecipeTemplate {
+    /// File name without extension
+    pub name: String,
+    pub content: String,
+}
+
+impl RecipeTemplate {
+    /// The template as a snippet, with the cursor placed at the end.
+    pub fn snippet(&self) -> String {
+        let mut snippet = String::with_capacity(self.content.len() + 2);
+        for c in self.content.chars() {
+            if matches!(c, '$' | '}' | '\\') {
+                snippet.push('\\');
+            }
+            snippet.push(c);
+        }
+        snippet.push_str("$0");
+        snippet
+    }
+}
+
+/// The `.cook` files in `dir`, sorted by name.
+pub fn load_templates(dir: &Path) -> Vec<RecipeTemplate> {
+    let mut templates = Vec::new();
+
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("cook") {
+                continue;
+            }
+            let (Some(name), Ok(content)) = (
+                path.file_stem().and_then(|s| s.to_str()),
+                std::fs::read_to_string(&path),
+            ) else {
+                continue;
+            };
+            tracing::debug!("Loaded recipe template {:?}", path);
+            templates.push(RecipeTemplate {
+                name: name.to_string(),
+                content,
+            });
+        }
+    }
+
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    templates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::parse_recipe;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn example_templates_parse() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("docs/templates");
+        let templates = load_templates(&dir);
+        let names: Vec<_> = templates.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["bake", "braise", "salad"]);
+        for template in &templates {
+            let name = &template.name;
+            assert!(
+                parse_recipe(&template.content).is_some(),
+                "{name} doesn't parse"
+            );
+        }
+    }
+
+    #[test]
+    fn workspace_templates() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("salad.cook"), "Toss @leaves{}.").unwrap();
+        fs::write(dir.path().join("soup.cook"), "Boil @water.").unwrap();
+        fs::write(dir.path().join("notes.md"), "").unwrap();
+
+        let templates = load_templates(dir.path());
+        let names: Vec<_> = templates.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["salad", "soup"]);
+        assert_eq!(templates[0].snippet(), "Toss @leaves{\\}.$0");
+        assert!(load_templates(&dir.path().join("missing")).is_empty());
+    }
+}
@@ -8,8 +8,8 @@
 //! recipe actually parses (multi-word names, modifiers, references, aliases,
 //! escapes and comments included).
 
-use cooklang::parser::{Event, PullParser};
-use cooklang::{Extensions, Span};
+use cooklang::parser::{Event, Modifiers, PullParser, Quantity};
+use cooklang::{Extensions, Span, Text};
 
 /// The kind of a located component.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -19,6 +19,20 @@ pub enum ComponentKind {
     Timer,
 }
 
+/// A piece of a component's source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentPart {
+    /// The marker (`@`, `#`, `~`) and any modifiers (`?`, `&(=1)`, ...)
+    Marker,
+    /// The name, or the alias after `|`
+    Name,
+    /// The quantity value, including a `=` scaling lock
+    Quantity,
+    Unit,
+    /// The text of a `(note)`, without the parentheses
+    Note,
+}
+
 /// A component found in the source, with its byte span and parsed name.
 #[derive(Debug, Clone)]
 pub struct Component {
@@ -27,6 +41,45 @@ pub struct Component {
     pub span: Span,
     /// Parsed, trimmed name. Empty for an unnamed timer (e.g. `~{10%min}`).
     pub name: String,
+    /// Byte ranges of the marker, name, quantity, unit and note, in source
+    /// order. Punctuation (`{`, `%`, `|`, ...) is not covered.
+    pub parts: Vec<(Span, ComponentPart)>,
+    /// Modifiers written after the marker (always empty for timers)
+    pub modifiers: Modifiers,
+}
+
+/// Span of `text` without surrounding whitespace.
+fn trimmed(content: &str, span: Span) -> Span {
+    let text = &content[span.range()];
+    let start = span.start() + (text.len() - text.trim_start().len());
+    Span::from(start..start + text.trim().len())
+}
+
+fn push_text(
+    content: &str,
+    parts: &mut Vec<(Span, ComponentPart)>,
+    text: &Text,
+    part: ComponentPart,
+) {
+    let span = trimmed(content, text.span());
+    if !span.is_empty() {
+        parts.push((span, part));
+    }
+}
+
+fn push_quantity(content: &str, parts: &mut Vec<(Span, ComponentPart)>, quantity: &Quantity) {
+    let value = quantity.value.span();
+    let start = quantity
+        .value
+        .scaling_lock
+        .map_or(value.start(), |lock| lock.start());
+    parts.push((
+        trimmed(content, Span::from(start..value.end())),
+        ComponentPart::Quantity,
+    ));
+    if let Some(ref unit) = quantity.unit {
+        push_text(content, parts, unit, ComponentPart::Unit);
+    }
 }
 
 /// Scans `content` with the Cooklang parser and returns every ingredient,
@@ -36,27 +89,72 @@ pub fn scan_components(content: &str) -> Vec<Component> {
     let mut components = Vec::new();
 
     for event in parser {
+        let mut parts = Vec::new();
         let component = match event {
             Event::Ingredient(located) => {
                 let span = located.span();
+                let ingredient = located.into_inner();
+                parts.push((
+                    Span::from(span.start()..ingredient.name.span().start()),
+                    ComponentPart::Marker,
+                ));
+                push_text(content, &mut parts, &ingredient.name, ComponentPart::Name);
+                if let Some(ref alias) = ingredient.alias {
+                    push_text(content, &mut parts, alias, ComponentPart::Name);
+                }
+                if let Some(ref quantity) = ingredient.quantity {
+                    push_quantity(content, &mut parts, quantity);
+                }
+                if let Some(ref note) = ingredient.note {
+                    push_text(content, &mut parts, note, ComponentPart::Note);
+                }
                 Component {
                     kind: ComponentKind::Ingredient,
                     span,
-                    name: located.into_inner().name.text_trimmed().into_owned(),
+                    name: ingredient.name.text_trimmed().into_owned(),
+                    parts,
+                    modifiers: *ingredient.modifiers,
                 }
             }
             Event::Cookware(located) => {
                 let span = located.span();
+                let cookware = located.into_inner();
+                parts.push((
+                    Span::from(span.start()..cookware.name.span().start()),
+                    ComponentPart::Marker,
+                ));
+                push_text(content, &mut parts, &cookware.name, ComponentPart::Name);
+                if let Some(ref alias) = cookware.alias {
+                    push_text(content, &mut parts, alias, ComponentPart::Name);
+                }
+                if let Some(ref quantity) = cookware.quantity {
+                    push_quantity(content, &mut parts, quantity);
+                }
+                if let Some(ref note) = cookware.note {
+                    push_text(content, &mut parts, note, ComponentPart::Note);
+                }
                 Component {
                     kind: ComponentKind::Cookware,
                     span,
-                    name: located.into_inner().name.text_trimmed().into_owned(),
+                    name: cookware.name.text_trimmed().into_owned(),
+                    parts,
+                    modifiers: *cookware.modifiers,
                 }
             }
             Event::Timer(located) => {
                 let span = located.span();
-                let name = located
-                    .into_inner()
+                let timer = located.into_inner();
+                parts.push((
+                    Span::from(span.start()..span.start() + 1),
+                    ComponentPart::Marker,
+                ));
+                if let Some(ref name) = timer.name {
+                    push_text(content, &mut parts, name, ComponentPart::Name);
+                }
+                if let Some(ref quantity) = timer.quantity {
+                    push_quantity(content, &mut parts, quantity);
+                }
+                let name = timer
                     .name
                     .map(|t| t.text_trimmed().into_owned())
                     .unwrap_or_default();
@@ -64,6 +162,8 @@ pub fn scan_components(content: &str) -> Vec<Component> {
                     kind: ComponentKind::Timer,
                     span,
                     name,
+                    parts,
+                    modifiers: Modifiers::empty(),
                 }
             }
             _ => continue,
@@ -151,6 +251,39 @@ mod tests {
         assert!(comps.is_empty(), "got: {comps:?}");
     }
 
+    #[test]
+    fn parts_of_a_component() {
+        let s = "Add @?salt|sel{=2 %tsp}(fine) then ~eggs{3%min}.";
+        let comps = scan_components(s);
+        let parts = |i: usize| -> Vec<(&str, ComponentPart)> {
+            comps[i]
+                .parts
+                .iter()
+                .map(|(span, part)| (&s[span.range()], *part))
+                .collect()
+        };
+        assert_eq!(
+            parts(0),
+            vec![
+                ("@?", ComponentPart::Marker),
+                ("salt", ComponentPart::Name),
+                ("sel", ComponentPart::Name),
+                ("=2", ComponentPart::Quantity),
+                ("tsp", ComponentPart::Unit),
+                ("fine", ComponentPart::Note),
+            ]
+        );
+        assert_eq!(
+            parts(1),
+            vec![
+                ("~", ComponentPart::Marker),
+                ("eggs", ComponentPart::Name),
+                ("3", ComponentPart::Quantity),
+                ("min", ComponentPart::Unit),
+            ]
+        );
+    }
+
     #[test]
     fn component_at_offset() {
         let s = "Chill @heavy whipping cream{1%cup}.";